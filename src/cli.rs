@@ -0,0 +1,281 @@
+use crate::hackmd;
+use crate::merge::UpdateMode;
+use crate::render::{ExtraNote, Renderer};
+use crate::tgdf::{self, file as agenda_file, Event};
+use clap::{Args, Parser, Subcommand};
+use reqwest::Url;
+use std::path::PathBuf;
+use std::time::Duration;
+
+#[derive(Parser)]
+#[clap(author, version, about, long_about = None)]
+pub struct Cli {
+    #[clap(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Scrape the agenda and write it to a file to be edited and fed back by --agenda-file
+    #[clap(alias = "export")]
+    Scrape {
+        #[clap(flatten)]
+        source: SourceArgs,
+        /// File to write the agenda to, defaults to the standard output
+        #[clap(long, short, value_parser, value_name = "FILE")]
+        output: Option<PathBuf>,
+        /// Format of the agenda, guessed from the output file extension if not given
+        #[clap(long, value_enum)]
+        format: Option<agenda_file::Format>,
+    },
+    /// Render every note into a directory as Markdown files without touching HackMD
+    Render {
+        #[clap(flatten)]
+        source: SourceArgs,
+        #[clap(flatten)]
+        templates: TemplateArgs,
+        /// Directory to write the rendered notes to
+        #[clap(long, value_parser, value_name = "DIR", default_value = "output")]
+        output_dir: PathBuf,
+    },
+    /// Create notes for the sessions which have none yet, leaving existing notes as they are
+    Publish {
+        #[clap(flatten)]
+        source: SourceArgs,
+        #[clap(flatten)]
+        templates: TemplateArgs,
+        #[clap(flatten)]
+        hackmd: HackMDArgs,
+    },
+    /// Update the notes created by previous runs and create the missing ones
+    Sync {
+        #[clap(flatten)]
+        source: SourceArgs,
+        #[clap(flatten)]
+        templates: TemplateArgs,
+        #[clap(flatten)]
+        hackmd: HackMDArgs,
+        /// How to update session notes created by previous runs
+        #[clap(long, value_enum, default_value_t = UpdateMode::Merge)]
+        update_mode: UpdateMode,
+    },
+    /// Delete every note recorded in the state file from HackMD
+    Clean {
+        #[clap(flatten)]
+        hackmd: HackMDArgs,
+        /// Delete the notes instead of only listing them
+        #[clap(long)]
+        yes: bool,
+    },
+    /// Compare the agenda with the notes recorded in the state file and on HackMD
+    Status {
+        #[clap(flatten)]
+        source: SourceArgs,
+        #[clap(flatten)]
+        hackmd: HackMDArgs,
+    },
+}
+
+impl Command {
+    /// Where the agenda of this command comes from, if it needs one.
+    pub fn source(&self) -> Option<&SourceArgs> {
+        match self {
+            Self::Scrape { source, .. }
+            | Self::Render { source, .. }
+            | Self::Publish { source, .. }
+            | Self::Sync { source, .. }
+            | Self::Status { source, .. } => Some(source),
+            Self::Clean { .. } => None,
+        }
+    }
+}
+
+/// Which event to read and how to read its agenda.
+#[derive(Args)]
+pub struct SourceArgs {
+    /// Year of the TGDF event to scrape
+    #[clap(long, value_parser, default_value_t = 2022)]
+    pub year: u32,
+    /// Base URL of the event site, defaults to https://<YEAR>.tgdf.tw
+    #[clap(long, value_parser, value_name = "URL")]
+    pub base_url: Option<Url>,
+    /// Read the agenda from a file written by `scrape` instead of scraping it
+    #[clap(long, value_parser, value_name = "FILE")]
+    pub agenda_file: Option<PathBuf>,
+    /// Parse the agenda from a directory of saved HTML pages instead of the live site
+    #[clap(long, value_parser, value_name = "DIR")]
+    pub snapshot: Option<PathBuf>,
+    /// Maximum number of concurrent requests to the event site
+    #[clap(long, value_parser, default_value_t = 4)]
+    pub crawl_concurrency: usize,
+    /// Delay between two requests to the event site in milliseconds
+    #[clap(long, value_parser, value_name = "MS", default_value_t = 200)]
+    pub crawl_delay: u64,
+    /// What to do when a session fails to be scraped
+    #[clap(long, value_enum, default_value_t = tgdf::ErrorPolicy::Fail)]
+    pub on_scrape_error: tgdf::ErrorPolicy,
+    /// Also create notes for sessions without speaker pages, e.g. panels and breaks
+    #[clap(long)]
+    pub include_speakerless: bool,
+    /// Also write the scrape report to this file as JSON
+    #[clap(long, value_parser, value_name = "FILE")]
+    pub report_json: Option<PathBuf>,
+    /// Directory to cache the pages of the event site in
+    #[clap(long, value_parser, value_name = "DIR", default_value = ".tgdf-cache")]
+    pub cache_dir: PathBuf,
+    /// Download every page again instead of revalidating the cached copies
+    #[clap(long)]
+    pub refresh: bool,
+}
+
+impl SourceArgs {
+    pub fn event(&self) -> Event {
+        match &self.base_url {
+            Some(base_url) => Event::with_base_url(self.year, base_url.clone()),
+            None => Event::new(self.year),
+        }
+    }
+
+    pub fn scrape_options(&self) -> tgdf::ScrapeOptions {
+        tgdf::ScrapeOptions {
+            policy: self.on_scrape_error,
+            include_speakerless: self.include_speakerless,
+        }
+    }
+}
+
+/// Templates the notes are rendered from.
+#[derive(Args)]
+pub struct TemplateArgs {
+    /// Directory of the templates, every `*.tera` file in it can be included by the others
+    #[clap(long, value_parser, value_name = "DIR", default_value = "templates")]
+    pub templates: PathBuf,
+    /// Template of the category note listing every session, relative to --templates
+    #[clap(
+        long,
+        value_parser,
+        value_name = "NAME",
+        default_value = "category.tera"
+    )]
+    pub category_template: String,
+    /// Template of the session notes, relative to --templates
+    #[clap(long, value_parser, value_name = "NAME", default_value = "note.tera")]
+    pub note_template: String,
+    /// YAML or JSON file of rules picking the template of each session note by its
+    /// tags, track, day or kind, relative to --templates, --note-template is the fallback.
+    /// The names of the tracks are also read from it
+    #[clap(long, value_parser, value_name = "NAME", default_value = "rules.yaml")]
+    pub template_rules: PathBuf,
+    /// Also create a note per speaker, linked to and from their session notes
+    #[clap(long)]
+    pub speaker_notes: bool,
+    /// Template of the speaker notes, relative to --templates
+    #[clap(
+        long,
+        value_parser,
+        value_name = "NAME",
+        default_value = "speaker.tera"
+    )]
+    pub speaker_template: String,
+    /// Also create an index note per day, linked from the category note
+    #[clap(long)]
+    pub day_notes: bool,
+    /// Template of the day notes, relative to --templates
+    #[clap(long, value_parser, value_name = "NAME", default_value = "day.tera")]
+    pub day_template: String,
+    /// Also create an index note per track, linked from the category note
+    #[clap(long)]
+    pub track_notes: bool,
+    /// Template of the track notes, relative to --templates
+    #[clap(long, value_parser, value_name = "NAME", default_value = "track.tera")]
+    pub track_template: String,
+}
+
+impl TemplateArgs {
+    pub fn renderer(&self) -> Result<Renderer, Box<dyn std::error::Error>> {
+        let mut renderer = Renderer::new(
+            &self.templates,
+            &self.category_template,
+            &self.note_template,
+        )?
+        .with_rules(&self.template_rules)?;
+        let extra = [
+            (
+                self.speaker_notes,
+                ExtraNote::Speaker,
+                &self.speaker_template,
+            ),
+            (self.day_notes, ExtraNote::Day, &self.day_template),
+            (self.track_notes, ExtraNote::Track, &self.track_template),
+        ];
+        for (enabled, kind, template) in extra {
+            if enabled {
+                renderer = renderer.with_extra_template(kind, template)?;
+            }
+        }
+        Ok(renderer)
+    }
+}
+
+/// How to reach HackMD and where the created notes are recorded.
+#[derive(Args)]
+pub struct HackMDArgs {
+    /// Path to the HackMD API token
+    #[clap(long, value_parser, value_name = "FILE")]
+    pub token_path: PathBuf,
+    /// Create the notes in this team workspace instead of the personal one
+    #[clap(long, value_parser, value_name = "TEAM_PATH")]
+    pub team: Option<String>,
+    /// Maximum number of concurrent HackMD API requests
+    #[clap(long, value_parser, default_value_t = 4)]
+    pub max_concurrency: usize,
+    /// How many times a rate limited or failed HackMD API request is retried
+    #[clap(long, value_parser, default_value_t = 5)]
+    pub max_retries: u32,
+    /// Delay before the first retry in milliseconds, doubled on every retry
+    #[clap(long, value_parser, value_name = "MS", default_value_t = 1000)]
+    pub retry_delay: u64,
+    /// File recording the notes created by previous runs, for one event and workspace
+    #[clap(
+        long,
+        value_parser,
+        value_name = "FILE",
+        default_value = "co-write-state.json"
+    )]
+    pub state: PathBuf,
+}
+
+impl HackMDArgs {
+    pub fn request_policy(&self) -> hackmd::RequestPolicy {
+        hackmd::RequestPolicy {
+            max_concurrency: self.max_concurrency,
+            max_retries: self.max_retries,
+            base_delay: Duration::from_millis(self.retry_delay),
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_subcommands() {
+        let cli = Cli::try_parse_from(["co-write", "export", "--year", "2023"]).unwrap();
+        assert!(matches!(cli.command, Command::Scrape { .. }));
+        assert_eq!(cli.command.source().unwrap().year, 2023);
+
+        let cli = Cli::try_parse_from(["co-write", "sync", "--token-path", "token"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Command::Sync {
+                update_mode: UpdateMode::Merge,
+                ..
+            }
+        ));
+
+        assert!(Cli::try_parse_from(["co-write", "publish"]).is_err());
+        assert!(Cli::try_parse_from(["co-write", "clean", "--year", "2023"]).is_err());
+    }
+}
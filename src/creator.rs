@@ -0,0 +1,608 @@
+use crate::merge::{self, UpdateMode};
+use crate::render::{ExtraNote, NoteKey, Renderer};
+use crate::state::State;
+use crate::target::{NewNote, Target};
+use crate::tgdf::{Agenda, Event, Presenter, Program, Session};
+use futures::future::join_all;
+use futures::stream::{FuturesUnordered, StreamExt};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
+
+/// Renders the notes of an event and publishes them to a [`Target`].
+pub struct CoWriteCreator {
+    target: Target,
+    event: Event,
+    program: Program,
+    renderer: Renderer,
+    /// How to update notes created by previous runs, `None` leaves them untouched.
+    update_mode: Option<UpdateMode>,
+}
+
+impl CoWriteCreator {
+    pub fn new(
+        target: Target,
+        event: Event,
+        renderer: Renderer,
+        update_mode: Option<UpdateMode>,
+    ) -> Self {
+        Self {
+            target,
+            event,
+            renderer,
+            update_mode,
+            program: Program::default(),
+        }
+    }
+
+    pub fn add_agenda(&mut self, agenda: Agenda) {
+        self.program.agendas.push(agenda);
+    }
+
+    /// Register a presenter referenced by the sessions by `id`, its key in
+    /// [`Program::speakers`].
+    pub fn add_speaker(&mut self, id: String, speaker: Presenter) {
+        self.program.speakers.insert(id, speaker);
+    }
+
+    fn sessions(&self) -> Vec<&Session> {
+        self.program.sessions()
+    }
+
+    /// Every note rendered for the program, the speaker, day and track notes
+    /// are only included if the renderer has their templates.
+    fn notes(&self) -> Vec<Note<'_>> {
+        let mut notes = self
+            .sessions()
+            .into_iter()
+            .map(Note::Session)
+            .collect::<Vec<_>>();
+        if self.renderer.renders(ExtraNote::Speaker) {
+            notes.extend(self.program.speakers.values().map(Note::Speaker));
+        }
+        if self.renderer.renders(ExtraNote::Day) {
+            notes.extend(
+                self.program
+                    .days()
+                    .map(|(day, agenda)| Note::Day(day, agenda)),
+            );
+        }
+        if self.renderer.renders(ExtraNote::Track) {
+            notes.extend(self.program.tracks().into_iter().map(Note::Track));
+        }
+        notes.push(Note::Category);
+        notes
+    }
+
+    /// Create the missing notes and update the existing ones according to the
+    /// update mode, recording the note IDs in `state`.
+    ///
+    /// The category, day, track and speaker notes are always rendered again,
+    /// since they list the sessions.
+    ///
+    /// `state` is saved to `state_path` as soon as each note is created, so an
+    /// interrupted run doesn't forget the notes it created. Notes that were
+    /// created before an error are still recorded, so the caller should save
+    /// `state` even if this fails.
+    pub async fn create(
+        &self,
+        state: &mut State,
+        state_path: Option<&Path>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let notes = self.notes();
+        // a broken pattern or colliding permalinks stop the run before
+        // anything is created
+        let mut new_notes = notes
+            .iter()
+            .map(|note| self.new_note(note))
+            .collect::<Result<Vec<_>, _>>()?;
+        disambiguate_names(&mut new_notes);
+        check_permalinks(&new_notes)?;
+        // notes of previous runs can be linked before they are updated
+        for note in &notes {
+            if let Some(id) = state.note_id(&note.key()) {
+                self.renderer.set_note_id(note.key(), id.clone());
+            }
+        }
+
+        // Notes link to each other, so the missing notes are created with the
+        // links known so far, and rendered again once every note has an ID.
+        let missing = notes
+            .iter()
+            .zip(&new_notes)
+            .filter(|(note, _)| state.note_id(&note.key()).is_none())
+            .collect::<Vec<_>>();
+        let first_contents = missing
+            .iter()
+            .map(|(note, _)| self.render_note(note, state))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut pending = missing
+            .iter()
+            .zip(first_contents)
+            .map(|((note, new_note), content)| async move {
+                let result = self.target.create(new_note, content.clone()).await;
+                (note, content, result)
+            })
+            .collect::<FuturesUnordered<_>>();
+        let mut created = HashMap::new();
+        let mut error = None;
+        while let Some((note, content, result)) = pending.next().await {
+            let result = result.and_then(|id| {
+                self.renderer.set_note_id(note.key(), id.clone());
+                state.set_note_id(note.key(), id);
+                created.insert(note.key(), content);
+                match state_path {
+                    Some(path) => state.save(path),
+                    None => Ok(()),
+                }
+            });
+            if let Err(err) = result {
+                error.get_or_insert(err);
+            }
+        }
+        if let Some(err) = error {
+            return Err(err);
+        }
+
+        let contents = notes
+            .iter()
+            .map(|note| self.render_note(note, state))
+            .collect::<Result<Vec<_>, _>>()?;
+        let results = join_all(notes.iter().zip(contents).map(|(note, content)| {
+            let id = state
+                .note_id(&note.key())
+                .expect("missing notes are created");
+            self.update_note(note, id, content, created.get(&note.key()))
+        }))
+        .await;
+        results.into_iter().collect::<Result<Vec<_>, _>>()?;
+
+        Ok(())
+    }
+
+    /// Update an existing note according to the update mode.
+    ///
+    /// Notes created by this run are only updated if their content changed
+    /// since they were created, i.e. they link to notes created after them.
+    async fn update_note(
+        &self,
+        note: &Note<'_>,
+        id: &str,
+        content: String,
+        created: Option<&String>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(created) = created {
+            if *created != content {
+                self.target.update(id, content).await?;
+            }
+            return Ok(());
+        }
+        let content = match self.update_mode {
+            _ if note.is_generated() => content,
+            None => return Ok(()),
+            Some(UpdateMode::Overwrite) => content,
+            Some(UpdateMode::Merge) => {
+                let current = self.target.content(id).await?;
+                match merge::merge(&current, &content) {
+                    Some(content) => content,
+                    None => {
+                        eprintln!(
+                            "skip updating note {} of {:?}: `{}` is missing",
+                            id,
+                            note.title(),
+                            merge::GENERATED_END
+                        );
+                        return Ok(());
+                    }
+                }
+            }
+        };
+        self.target.update(id, content).await
+    }
+
+    /// The name, title and permalink a note is created with, only session
+    /// notes have titles and permalinks.
+    fn new_note(&self, note: &Note<'_>) -> Result<NewNote, Box<dyn std::error::Error>> {
+        let (title, permalink) = match note {
+            Note::Session(session) => {
+                let presenters = self.program.presenters(session);
+                (
+                    self.renderer
+                        .session_title(session, &presenters, &self.event)?,
+                    self.renderer
+                        .session_permalink(session, &presenters, &self.event)?,
+                )
+            }
+            _ => (None, None),
+        };
+        Ok(NewNote {
+            name: note.name(),
+            title,
+            permalink,
+        })
+    }
+
+    fn render_note(
+        &self,
+        note: &Note<'_>,
+        state: &State,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        match note {
+            Note::Session(session) => self.gen_session_note_content(session),
+            Note::Speaker(speaker) => {
+                let mut context = tera::Context::new();
+                context.insert("speaker", &self.speaker_value(speaker, state));
+                context.insert("event", &self.event);
+                self.renderer.extra(ExtraNote::Speaker, &context)
+            }
+            Note::Day(day, agenda) => {
+                let mut context = tera::Context::new();
+                context.insert("day", day);
+                context.insert("agenda", &self.agenda_value(agenda, state, |_| true));
+                context.insert("event", &self.event);
+                self.renderer.extra(ExtraNote::Day, &context)
+            }
+            Note::Track(track) => {
+                // the days of the track, with the sessions of other tracks left out
+                let agendas = self
+                    .program
+                    .agendas
+                    .iter()
+                    .map(|a| self.agenda_value(a, state, |s| s.track == *track))
+                    .collect::<Vec<_>>();
+                let mut context = tera::Context::new();
+                context.insert("track", track);
+                context.insert("agendas", &agendas);
+                context.insert("event", &self.event);
+                self.renderer.extra(ExtraNote::Track, &context)
+            }
+            Note::Category => {
+                let agendas = self
+                    .program
+                    .agendas
+                    .iter()
+                    .map(|a| self.agenda_value(a, state, |_| true))
+                    .collect::<Vec<_>>();
+                // every speaker with the sessions they present, for a speakers index
+                let speakers = self
+                    .program
+                    .speakers
+                    .values()
+                    .map(|speaker| self.speaker_value(speaker, state))
+                    .collect::<Vec<_>>();
+                // the day and track notes, empty if they aren't created
+                let days = self
+                    .program
+                    .days()
+                    .filter_map(|(day, agenda)| {
+                        let id = state.days.get(&day)?;
+                        Some(json!({ "day": day, "date": agenda.date, "note_id": id }))
+                    })
+                    .collect::<Vec<_>>();
+                let tracks = self
+                    .program
+                    .tracks()
+                    .into_iter()
+                    .filter_map(|track| {
+                        let id = state.tracks.get(&track)?;
+                        Some(json!({ "track": track, "note_id": id }))
+                    })
+                    .collect::<Vec<_>>();
+                self.renderer.category(&tera::Context::from_value(json!({
+                    "agendas": agendas,
+                    "speakers": speakers,
+                    "days": days,
+                    "tracks": tracks,
+                    "event": &self.event,
+                }))?)
+            }
+        }
+    }
+
+    /// An agenda with the sessions that pass `filter`, leaving out the periods
+    /// without any.
+    fn agenda_value(
+        &self,
+        agenda: &Agenda,
+        state: &State,
+        filter: impl Fn(&Session) -> bool,
+    ) -> Value {
+        let periods = agenda
+            .periods
+            .iter()
+            .filter_map(|p| {
+                let sessions = p
+                    .sessions
+                    .iter()
+                    .filter(|s| filter(s))
+                    .map(|s| self.session_value(s, state))
+                    .collect::<Vec<_>>();
+                if sessions.is_empty() {
+                    return None;
+                }
+                let mut p = json!(p);
+                p["sessions"] = json!(sessions);
+                Some(p)
+            })
+            .collect::<Vec<_>>();
+        let mut a = json!(agenda);
+        a["periods"] = json!(periods);
+        a
+    }
+
+    /// A session as the index and speaker templates see it, with its
+    /// presenters and note ID.
+    fn session_value(&self, session: &Session, state: &State) -> Value {
+        let mut value = json!(session);
+        value["presenters"] = json!(self.program.presenters(session));
+        value["note_id"] = json!(state.sessions.get(&session.key()));
+        value
+    }
+
+    /// A speaker with the sessions they present and their note ID.
+    fn speaker_value(&self, speaker: &Presenter, state: &State) -> Value {
+        let sessions = self
+            .sessions()
+            .into_iter()
+            .filter(|s| s.speakers.contains(&speaker.id))
+            .map(|s| self.session_value(s, state))
+            .collect::<Vec<_>>();
+        let mut value = json!(speaker);
+        value["sessions"] = json!(sessions);
+        value["note_id"] = json!(state.speakers.get(&speaker.id));
+        value
+    }
+
+    pub(crate) fn gen_session_note_content(
+        &self,
+        session: &Session,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        self.renderer
+            .session(session, &self.program.presenters(session), &self.event)
+    }
+}
+
+/// Sessions sharing a slot get the same name, so the later ones are suffixed
+/// with `-2`, `-3`, ... in agenda order.
+fn disambiguate_names(notes: &mut [NewNote]) {
+    let mut taken = HashSet::new();
+    for note in notes {
+        let base = note.name.clone();
+        let mut n = 1;
+        while !taken.insert(note.name.clone()) {
+            n += 1;
+            note.name = format!("{}-{}", base, n);
+        }
+    }
+}
+
+/// Make sure no two notes get the same permalink, listing every collision.
+fn check_permalinks(notes: &[NewNote]) -> Result<(), Box<dyn std::error::Error>> {
+    let mut names = BTreeMap::<&str, Vec<&str>>::new();
+    for note in notes {
+        if let Some(permalink) = &note.permalink {
+            names.entry(permalink).or_default().push(&note.name);
+        }
+    }
+    let collisions = names
+        .iter()
+        .filter(|(_, names)| names.len() > 1)
+        .map(|(permalink, names)| format!("{} is generated for {}", permalink, names.join(", ")))
+        .collect::<Vec<_>>();
+    if !collisions.is_empty() {
+        return Err(format!("colliding permalinks: {}", collisions.join("; ")).into());
+    }
+    Ok(())
+}
+
+/// A note rendered for the program.
+enum Note<'a> {
+    Session(&'a Session),
+    Speaker(&'a Presenter),
+    Day(u32, &'a Agenda),
+    Track(u32),
+    Category,
+}
+
+impl Note<'_> {
+    fn key(&self) -> NoteKey {
+        match self {
+            Self::Session(session) => NoteKey::Session(session.key()),
+            Self::Speaker(speaker) => NoteKey::Speaker(speaker.id.clone()),
+            Self::Day(day, _) => NoteKey::Day(*day),
+            Self::Track(track) => NoteKey::Track(*track),
+            Self::Category => NoteKey::Category,
+        }
+    }
+
+    /// Index and speaker notes are generated from the agenda only, so they
+    /// are always overwritten whatever the update mode is.
+    fn is_generated(&self) -> bool {
+        matches!(
+            self,
+            Self::Speaker(_) | Self::Day(..) | Self::Track(_) | Self::Category
+        )
+    }
+
+    /// Name of the note when it is written to a directory, sessions sharing a
+    /// slot share it until [`disambiguate_names`].
+    fn name(&self) -> String {
+        match self {
+            Self::Session(session) => session.slug(),
+            Self::Speaker(speaker) => format!("speaker-{}", speaker.id),
+            Self::Day(day, _) => format!("day-{}", day),
+            Self::Track(track) => format!("track-{}", track),
+            Self::Category => "category".to_string(),
+        }
+    }
+
+    fn title(&self) -> &str {
+        match self {
+            Self::Session(session) => &session.title,
+            Self::Speaker(speaker) => &speaker.name,
+            Self::Day(..) | Self::Track(_) | Self::Category => "index",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tgdf::{Period, SessionKind};
+    use std::fs;
+
+    fn new_note(name: &str, permalink: Option<&str>) -> NewNote {
+        NewNote {
+            name: name.to_string(),
+            title: None,
+            permalink: permalink.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_disambiguate_names() {
+        let mut notes = vec![
+            new_note("d1-t1-1000", None),
+            new_note("d1-t1-1000", None),
+            new_note("d1-t1-1100", None),
+            new_note("d1-t1-1000", None),
+        ];
+        disambiguate_names(&mut notes);
+        let names = notes.iter().map(|n| n.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            ["d1-t1-1000", "d1-t1-1000-2", "d1-t1-1100", "d1-t1-1000-3"]
+        );
+    }
+
+    #[test]
+    fn test_check_permalinks() {
+        let mut notes = vec![
+            new_note("d1-t1-1000", Some("tgdf2022-d1-t1")),
+            new_note("d1-t2-1000", Some("tgdf2022-d1-t2")),
+            new_note("category", None),
+            new_note("day-1", None),
+        ];
+        check_permalinks(&notes).unwrap();
+
+        notes.push(new_note("d1-t1-1100", Some("tgdf2022-d1-t1")));
+        let err = check_permalinks(&notes).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "colliding permalinks: tgdf2022-d1-t1 is generated for d1-t1-1000, d1-t1-1100"
+        );
+    }
+
+    fn creator(dir: &Path, update_mode: UpdateMode, sessions: Vec<Session>) -> CoWriteCreator {
+        let renderer = Renderer::new(Path::new("templates"), "category.tera", "note.tera")
+            .unwrap()
+            .with_extra_template(ExtraNote::Speaker, "speaker.tera")
+            .unwrap()
+            .with_extra_template(ExtraNote::Day, "day.tera")
+            .unwrap();
+        let mut creator = CoWriteCreator::new(
+            Target::Local(dir.to_path_buf()),
+            Event::new(2022),
+            renderer,
+            Some(update_mode),
+        );
+        let periods = sessions
+            .into_iter()
+            .map(|session| Period {
+                time: session.time,
+                sessions: vec![session],
+            })
+            .collect();
+        creator.add_agenda(Agenda {
+            date: "8/5".to_string(),
+            periods,
+        });
+        creator
+    }
+
+    fn level_design(description: &str) -> Session {
+        Session::test(1, 2, ("10:30", "11:10"), "關卡設計實戰")
+            .with_url("https://2022.tgdf.tw/agenda/level-design")
+            .with_description(description)
+    }
+
+    #[tokio::test]
+    async fn test_create_twice() {
+        let dir = std::env::temp_dir().join(format!("co-write-create-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let state_path = dir.with_extension("json");
+        let mut state = State::default();
+
+        creator(&dir, UpdateMode::Overwrite, vec![level_design("第一版")])
+            .create(&mut state, Some(&state_path))
+            .await
+            .unwrap();
+        let ids = state.notes();
+        assert_eq!(ids.len(), 3);
+        let note = dir.join("d1-t2-1030.md");
+        assert!(fs::read_to_string(&note).unwrap().contains("第一版"));
+
+        // the next run starts from the state saved by the first one
+        let mut state = State::load(&state_path).unwrap();
+        creator(&dir, UpdateMode::Overwrite, vec![level_design("第二版")])
+            .create(&mut state, None)
+            .await
+            .unwrap();
+        assert_eq!(state.notes(), ids);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 3);
+        assert!(fs::read_to_string(&note).unwrap().contains("第二版"));
+
+        fs::remove_dir_all(&dir).unwrap();
+        fs::remove_file(&state_path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_create_breaks() {
+        let dir = std::env::temp_dir().join(format!("co-write-breaks-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let mut state = State::default();
+        let sessions = vec![
+            Session::test(1, 1, ("10:10", "10:30"), "休息").with_kind(SessionKind::Break),
+            Session::test(1, 1, ("15:00", "15:20"), "休息").with_kind(SessionKind::Break),
+        ];
+
+        creator(&dir, UpdateMode::Overwrite, sessions)
+            .create(&mut state, None)
+            .await
+            .unwrap();
+        assert_eq!(
+            state.sessions.values().collect::<Vec<_>>(),
+            ["d1-t1-1010", "d1-t1-1500"]
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_sync_speakers() {
+        let dir = std::env::temp_dir().join(format!("co-write-speakers-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let mut state = State::default();
+        let creator = |introduction: &str| {
+            let mut creator = creator(
+                &dir,
+                UpdateMode::Merge,
+                vec![level_design("").with_speakers(&["alice"])],
+            );
+            let mut alice = Presenter::guest("Alice".to_string(), None);
+            alice.id = "alice".to_string();
+            alice.introduction = introduction.to_string();
+            creator.add_speaker(alice.id.clone(), alice);
+            creator
+        };
+
+        creator("intro v1").create(&mut state, None).await.unwrap();
+        let note = dir.join("speaker-alice.md");
+        assert!(fs::read_to_string(&note).unwrap().contains("intro v1"));
+
+        creator("intro v2").create(&mut state, None).await.unwrap();
+        assert!(fs::read_to_string(&note).unwrap().contains("intro v2"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,11 +1,53 @@
-use super::{note::NoteApi, team::TeamApi, user::UserApi};
-use reqwest::{RequestBuilder, Url};
+use super::{note::NoteApi, team::TeamApi, user::UserApi, Error, Result};
+use reqwest::{Method, RequestBuilder, Response, Url};
+use serde::de::DeserializeOwned;
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tokio::sync::Semaphore;
+
+/// How requests are scheduled to stay within HackMD's rate limits.
+#[derive(Debug, Clone)]
+pub struct RequestPolicy {
+    /// Maximum number of requests in flight.
+    pub max_concurrency: usize,
+    /// How many times a rate limited or failed request is retried.
+    pub max_retries: u32,
+    /// Delay before the first retry, doubled on every following retry.
+    pub base_delay: Duration,
+    /// Upper bound of the delay between retries.
+    pub max_delay: Duration,
+}
+
+impl Default for RequestPolicy {
+    fn default() -> Self {
+        Self {
+            max_concurrency: 4,
+            max_retries: 5,
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(60),
+        }
+    }
+}
+
+impl RequestPolicy {
+    fn backoff(&self, attempt: u32) -> Duration {
+        self.base_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_delay)
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct Client {
     client: reqwest::Client,
     base_url: Url,
     token: String,
+    policy: RequestPolicy,
+    permits: Arc<Semaphore>,
+    /// Set when the API asks us to back off, every request waits until then.
+    paused_until: Arc<Mutex<Option<Instant>>>,
 }
 
 macro_rules! impl_http_method {
@@ -20,12 +62,20 @@ macro_rules! impl_http_method {
 }
 
 impl Client {
-    pub async fn new(token: &str) -> Result<Self, Box<dyn std::error::Error>> {
+    #[allow(dead_code)]
+    pub async fn new(token: &str) -> Result<Self> {
+        Self::with_policy(token, RequestPolicy::default()).await
+    }
+
+    pub async fn with_policy(token: &str, policy: RequestPolicy) -> Result<Self> {
         let client = reqwest::Client::new();
         let client = Self {
             client,
-            base_url: "https://api.hackmd.io".parse::<Url>()?,
+            base_url: "https://api.hackmd.io".parse::<Url>().unwrap(),
             token: token.to_string(),
+            permits: Arc::new(Semaphore::new(policy.max_concurrency.max(1))),
+            policy,
+            paused_until: Arc::new(Mutex::new(None)),
         };
         client.user().me().await?;
         Ok(client)
@@ -37,15 +87,109 @@ impl Client {
     impl_http_method!(patch);
     impl_http_method!(delete);
 
-    pub fn note(&self) -> NoteApi {
+    /// Send the request, turning non-success statuses into [`Error`]s.
+    ///
+    /// Rate limited requests and connection failures are retried with
+    /// exponential backoff according to the [`RequestPolicy`]. Server errors
+    /// and timeouts are only retried for idempotent requests, since a `POST`
+    /// may have created a note before failing.
+    pub(crate) async fn send(&self, request: RequestBuilder) -> Result<Response> {
+        let request = request.build()?;
+        let idempotent = request.method() != Method::POST;
+        let _permit = self.permits.acquire().await.unwrap();
+        let mut attempt = 0;
+        loop {
+            self.wait_for_pause().await;
+            // requests with a streaming body can't be cloned and are sent once
+            let retry = match request.try_clone() {
+                Some(retry) if attempt < self.policy.max_retries => retry,
+                _ => return Self::check(self.client.execute(request).await).await,
+            };
+            let err = match Self::check(self.client.execute(retry).await).await {
+                Ok(response) => return Ok(response),
+                Err(err) => err,
+            };
+            let delay = match &err {
+                Error::RateLimited { retry_after } => {
+                    let delay = retry_after
+                        .map(|delay| delay.min(self.policy.max_delay))
+                        .unwrap_or_else(|| self.policy.backoff(attempt));
+                    self.pause(delay);
+                    delay
+                }
+                Error::Server { .. } if idempotent => self.policy.backoff(attempt),
+                // a failed connection never reached the server
+                Error::Transport(e) if e.is_connect() || (idempotent && e.is_timeout()) => {
+                    self.policy.backoff(attempt)
+                }
+                _ => return Err(err),
+            };
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    async fn check(response: reqwest::Result<Response>) -> Result<Response> {
+        let response = response?;
+        if response.status().is_success() {
+            Ok(response)
+        } else {
+            Err(Error::from_response(response).await)
+        }
+    }
+
+    /// Hold off all requests for `delay`.
+    fn pause(&self, delay: Duration) {
+        let until = Instant::now() + delay;
+        let mut paused_until = self.paused_until.lock().unwrap();
+        if paused_until.is_none_or(|t| t < until) {
+            *paused_until = Some(until);
+        }
+    }
+
+    async fn wait_for_pause(&self) {
+        let until = *self.paused_until.lock().unwrap();
+        if let Some(until) = until {
+            tokio::time::sleep_until(until.into()).await;
+        }
+    }
+
+    /// Send the request and decode the JSON response body.
+    pub(crate) async fn send_json<T: DeserializeOwned>(
+        &self,
+        request: RequestBuilder,
+    ) -> Result<T> {
+        let body = self.send(request).await?.text().await?;
+        Ok(serde_json::from_str(&body)?)
+    }
+
+    pub fn note(&self) -> NoteApi<'_> {
         NoteApi::new(self)
     }
 
-    pub fn user(&self) -> UserApi {
+    pub fn user(&self) -> UserApi<'_> {
         UserApi::new(self)
     }
 
-    pub fn team(&self) -> TeamApi {
+    pub fn team(&self) -> TeamApi<'_> {
         TeamApi::new(self)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff() {
+        let policy = RequestPolicy {
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(3),
+            ..Default::default()
+        };
+        assert_eq!(policy.backoff(0), Duration::from_millis(500));
+        assert_eq!(policy.backoff(2), Duration::from_secs(2));
+        assert_eq!(policy.backoff(3), Duration::from_secs(3));
+        assert_eq!(policy.backoff(40), Duration::from_secs(3));
+    }
+}
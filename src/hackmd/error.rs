@@ -0,0 +1,180 @@
+use reqwest::{
+    header::{HeaderMap, RETRY_AFTER},
+    Response, StatusCode,
+};
+use std::{
+    fmt::Display,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Errors returned by the HackMD API client.
+#[derive(Debug)]
+pub enum Error {
+    /// The API token is missing or invalid.
+    Unauthorized,
+    /// The token is valid but not allowed to access the resource.
+    Forbidden,
+    NotFound,
+    /// Too many requests, `retry_after` is taken from the `Retry-After` or
+    /// `X-RateLimit-Reset` header.
+    RateLimited {
+        retry_after: Option<Duration>,
+    },
+    /// The API rejected the request, with the message it responded.
+    Validation {
+        status: StatusCode,
+        message: String,
+    },
+    /// The API failed to handle the request.
+    Server {
+        status: StatusCode,
+        message: String,
+    },
+    /// The request could not be sent or the response could not be read.
+    Transport(reqwest::Error),
+    /// The response body is not what the API is documented to return.
+    Decode(serde_json::Error),
+}
+
+impl Error {
+    /// Build the error from a response with a non-success status.
+    pub(crate) async fn from_response(response: Response) -> Self {
+        let status = response.status();
+        match status {
+            StatusCode::UNAUTHORIZED => return Self::Unauthorized,
+            StatusCode::FORBIDDEN => return Self::Forbidden,
+            StatusCode::NOT_FOUND => return Self::NotFound,
+            StatusCode::TOO_MANY_REQUESTS => {
+                let retry_after = retry_after(response.headers());
+                return Self::RateLimited { retry_after };
+            }
+            _ => {}
+        }
+        let message = match response.text().await {
+            Ok(body) => extract_message(body),
+            Err(err) => return Self::Transport(err),
+        };
+        if status.is_server_error() {
+            Self::Server { status, message }
+        } else {
+            Self::Validation { status, message }
+        }
+    }
+}
+
+/// `X-RateLimit-Reset` values from here on are UNIX timestamps rather than
+/// seconds, i.e. anything after September 2001.
+const TIMESTAMP_THRESHOLD: u64 = 1_000_000_000;
+
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let header = |name| {
+        headers
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.trim().parse::<u64>().ok())
+    };
+    if let Some(secs) = header(RETRY_AFTER.as_str()) {
+        return Some(Duration::from_secs(secs));
+    }
+    // either a UNIX timestamp or seconds until the limit resets
+    let reset = header("x-ratelimit-reset")?;
+    if reset < TIMESTAMP_THRESHOLD {
+        return Some(Duration::from_secs(reset));
+    }
+    // a timestamp in the past, e.g. because of clock skew, is no wait at all
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs();
+    Some(Duration::from_secs(reset.saturating_sub(now)))
+}
+
+/// Use the `message` or `error` field of a JSON error body if there is one.
+fn extract_message(body: String) -> String {
+    serde_json::from_str::<serde_json::Value>(&body)
+        .ok()
+        .and_then(|v| {
+            ["message", "error"]
+                .into_iter()
+                .find_map(|k| v.get(k).and_then(|m| m.as_str()).map(String::from))
+        })
+        .unwrap_or(body)
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Unauthorized => write!(f, "unauthorized, check the HackMD API token"),
+            Self::Forbidden => write!(f, "forbidden"),
+            Self::NotFound => write!(f, "not found"),
+            Self::RateLimited {
+                retry_after: Some(retry_after),
+            } => write!(f, "rate limited, retry after {}s", retry_after.as_secs()),
+            Self::RateLimited { retry_after: None } => write!(f, "rate limited"),
+            Self::Validation { status, message } | Self::Server { status, message } => {
+                write!(f, "{}: {}", status, message)
+            }
+            Self::Transport(err) => write!(f, "transport error: {}", err),
+            Self::Decode(err) => write!(f, "unexpected response: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Transport(err) => Some(err),
+            Self::Decode(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(err: reqwest::Error) -> Self {
+        Self::Transport(err)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        Self::Decode(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_after() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(retry_after(&headers), None);
+        headers.insert("x-ratelimit-reset", "30".parse().unwrap());
+        assert_eq!(retry_after(&headers), Some(Duration::from_secs(30)));
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let reset = |secs: u64| secs.to_string().parse().unwrap();
+        headers.insert("x-ratelimit-reset", reset(now - 1));
+        assert_eq!(retry_after(&headers), Some(Duration::ZERO));
+        headers.insert("x-ratelimit-reset", reset(now + 3600));
+        let delay = retry_after(&headers).unwrap();
+        assert!(delay > Duration::from_secs(3590) && delay <= Duration::from_secs(3600));
+        headers.insert(RETRY_AFTER, "5".parse().unwrap());
+        assert_eq!(retry_after(&headers), Some(Duration::from_secs(5)));
+    }
+
+    #[test]
+    fn test_extract_message() {
+        assert_eq!(
+            extract_message(r#"{"message": "title is too long"}"#.to_string()),
+            "title is too long"
+        );
+        assert_eq!(
+            extract_message(r#"{"error": "bad request"}"#.to_string()),
+            "bad request"
+        );
+        assert_eq!(extract_message("oops".to_string()), "oops");
+    }
+}
@@ -1,7 +1,10 @@
 mod client;
+mod error;
 mod note;
 pub mod permission;
 mod team;
 mod user;
 
-pub use client::Client;
+pub use client::{Client, RequestPolicy};
+pub use error::{Error, Result};
+pub use note::NoteApi;
@@ -1,4 +1,4 @@
-use super::{permission, Client};
+use super::{permission, Client, Result};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 
@@ -6,25 +6,34 @@ use serde_json::json;
 pub struct NoteBuilder<'a> {
     #[serde(skip)]
     client: &'a Client,
+    #[serde(skip)]
+    path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
     title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     content: Option<String>,
-    #[serde(rename = "commentPermission")]
+    #[serde(rename = "commentPermission", skip_serializing_if = "Option::is_none")]
     comment_permission: Option<permission::Comment>,
-    #[serde(rename = "readPermission")]
+    #[serde(rename = "readPermission", skip_serializing_if = "Option::is_none")]
     read_permission: Option<permission::Read>,
-    #[serde(rename = "writePermission")]
+    #[serde(rename = "writePermission", skip_serializing_if = "Option::is_none")]
     write_permission: Option<permission::Write>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    permalink: Option<String>,
 }
 
 impl<'a> NoteBuilder<'a> {
-    pub fn new(client: &'a Client) -> Self {
+    /// `path` is the endpoint to create the note with, e.g. `/v1/notes`.
+    pub fn new(client: &'a Client, path: String) -> Self {
         Self {
             client,
+            path,
             title: None,
             content: None,
             comment_permission: None,
             read_permission: None,
             write_permission: None,
+            permalink: None,
         }
     }
 
@@ -38,27 +47,34 @@ impl<'a> NoteBuilder<'a> {
         self
     }
 
+    #[allow(dead_code)]
     pub fn comment_permission(mut self, comment_permission: permission::Comment) -> Self {
         self.comment_permission = Some(comment_permission);
         self
     }
 
+    #[allow(dead_code)]
     pub fn read_permission(mut self, read_permission: permission::Read) -> Self {
         self.read_permission = Some(read_permission);
         self
     }
 
+    #[allow(dead_code)]
     pub fn write_permission(mut self, write_permission: permission::Write) -> Self {
         self.write_permission = Some(write_permission);
         self
     }
 
-    pub async fn done(self) -> Result<Note, Box<dyn std::error::Error>> {
-        let payload = json!(self);
-        let response = self.client.post("/v1/notes").json(&payload).send().await?;
-        let note = response.json::<Note>().await?;
+    pub fn permalink(mut self, permalink: String) -> Self {
+        self.permalink = Some(permalink);
+        self
+    }
 
-        Ok(note)
+    pub async fn done(self) -> Result<Note> {
+        let payload = json!(self);
+        self.client
+            .send_json(self.client.post(&self.path).json(&payload))
+            .await
     }
 }
 
@@ -67,20 +83,23 @@ pub struct UpdateNote<'a> {
     #[serde(skip)]
     client: &'a Client,
     #[serde(skip)]
-    id: String,
+    path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
     content: Option<String>,
-    #[serde(rename = "readPermission")]
+    #[serde(rename = "readPermission", skip_serializing_if = "Option::is_none")]
     read_permission: Option<permission::Read>,
-    #[serde(rename = "writePermission")]
+    #[serde(rename = "writePermission", skip_serializing_if = "Option::is_none")]
     write_permission: Option<permission::Write>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     permalink: Option<String>,
 }
 
 impl<'a> UpdateNote<'a> {
-    pub fn new(client: &'a Client, id: String) -> Self {
+    /// `path` is the endpoint of the note, e.g. `/v1/notes/{id}`.
+    pub fn new(client: &'a Client, path: String) -> Self {
         Self {
             client,
-            id,
+            path,
             content: None,
             read_permission: None,
             write_permission: None,
@@ -93,27 +112,28 @@ impl<'a> UpdateNote<'a> {
         self
     }
 
+    #[allow(dead_code)]
     pub fn read_permission(mut self, read_permission: permission::Read) -> Self {
         self.read_permission = Some(read_permission);
         self
     }
 
+    #[allow(dead_code)]
     pub fn write_permission(mut self, write_permission: permission::Write) -> Self {
         self.write_permission = Some(write_permission);
         self
     }
 
+    #[allow(dead_code)]
     pub fn permalink(mut self, permalink: String) -> Self {
         self.permalink = Some(permalink);
         self
     }
 
-    pub async fn done(self) -> Result<(), Box<dyn std::error::Error>> {
+    pub async fn done(self) -> Result<()> {
         let payload = json!(self);
         self.client
-            .patch(&format!("/v1/notes/{}", self.id))
-            .json(&payload)
-            .send()
+            .send(self.client.patch(&self.path).json(&payload))
             .await?;
 
         Ok(())
@@ -148,53 +168,110 @@ pub struct Note {
     pub publish_link: String,
 }
 
+/// A single note as returned by `GET /v1/notes/{id}`, which comes with its
+/// content unlike the note list.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NoteDetail {
+    #[serde(flatten)]
+    pub note: Note,
+    pub content: String,
+    pub description: Option<String>,
+    #[serde(rename = "lastChangedUser")]
+    pub last_changed_user: Option<LastChangedUser>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LastChangedUser {
+    pub name: String,
+    pub photo: String,
+    pub biography: Option<String>,
+    #[serde(rename = "userPath")]
+    pub user_path: String,
+}
+
+/// Notes of either the user or a team, depending on the endpoint prefix.
 pub struct NoteApi<'a> {
     client: &'a Client,
+    base: String,
 }
 
 impl<'a> NoteApi<'a> {
     pub fn new(client: &'a Client) -> Self {
-        Self { client }
+        Self {
+            client,
+            base: "/v1/notes".to_string(),
+        }
     }
 
-    pub async fn get(&self, id: &str) -> Result<Note, Box<dyn std::error::Error>> {
-        let note = self
-            .client
-            .get(&format!("/v1/notes/{}", id))
-            .send()
-            .await?
-            .json::<Note>()
-            .await?;
+    /// Notes in the workspace of the team at `team_path`.
+    pub fn team(client: &'a Client, team_path: &str) -> Self {
+        Self {
+            client,
+            base: format!("/v1/teams/{}/notes", team_path),
+        }
+    }
 
-        Ok(note)
+    fn path(&self, id: &str) -> String {
+        format!("{}/{}", self.base, id)
     }
 
-    pub async fn get_list(&self) -> Result<Vec<Note>, Box<dyn std::error::Error>> {
-        let notes = self
-            .client
-            .get("/v1/notes")
-            .send()
-            .await?
-            .json::<Vec<Note>>()
-            .await?;
+    pub async fn get(&self, id: &str) -> Result<NoteDetail> {
+        self.client.send_json(self.client.get(&self.path(id))).await
+    }
 
-        Ok(notes)
+    pub async fn get_list(&self) -> Result<Vec<Note>> {
+        self.client.send_json(self.client.get(&self.base)).await
     }
 
-    pub fn builder(&self) -> NoteBuilder {
-        NoteBuilder::new(&self.client)
+    pub fn builder(&self) -> NoteBuilder<'a> {
+        NoteBuilder::new(self.client, self.base.clone())
     }
 
-    pub async fn delete(&self, id: &str) -> Result<(), Box<dyn std::error::Error>> {
-        self.client
-            .delete(&format!("/v1/notes/{}", id))
-            .send()
-            .await?;
+    pub async fn delete(&self, id: &str) -> Result<()> {
+        self.client.send(self.client.delete(&self.path(id))).await?;
 
         Ok(())
     }
 
-    pub fn update(&self, id: String) -> UpdateNote {
-        UpdateNote::new(&self.client, id)
+    pub fn update(&self, id: String) -> UpdateNote<'a> {
+        UpdateNote::new(self.client, self.path(&id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_deserialize_note_detail() {
+        let note = json!({
+            "id": "ABC1DEF2GHI3",
+            "title": "Personal note title",
+            "tags": ["personal", "note"],
+            "createdAt": 1643270371245u64,
+            "publishType": "view",
+            "publishedAt": null,
+            "permalink": null,
+            "shortId": "SJ6_6pM8t",
+            "content": "# Personal note title",
+            "lastChangedAt": 1643270452480u64,
+            "lastChangedUser": {
+                "name": "James",
+                "photo": "https://avatars.githubusercontent.com/u/26138990?s=96",
+                "biography": null,
+                "userPath": "1BQU8d-JT9m-4k2RsC1zOq"
+            },
+            "userPath": "1BQU8d-JT9m-4k2RsC1zOq",
+            "teamPath": null,
+            "readPermission": "guest",
+            "writePermission": "owner",
+            "publishLink": "https://hackmd.io/@username/permalink-of-the-note"
+        });
+        let note = serde_json::from_value::<NoteDetail>(note).unwrap();
+        assert_eq!(note.note.id, "ABC1DEF2GHI3");
+        assert_eq!(note.content, "# Personal note title");
+        assert!(note.description.is_none());
+        assert_eq!(note.last_changed_user.unwrap().name, "James");
     }
 }
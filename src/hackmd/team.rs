@@ -1,18 +1,20 @@
-use super::Client;
-use serde::{Deserialize, Serialize};
+use super::{note::NoteApi, Client, Result};
+use serde::Deserialize;
 
+// mirrors the API response, only the path is used so far
+#[allow(dead_code)]
 #[derive(Debug, Clone, Deserialize)]
 pub struct Team {
-    id: String,
+    pub id: String,
     #[serde(rename = "ownerId")]
-    owner_id: String,
-    path: String,
-    name: String,
-    logo: String,
-    description: Option<String>,
-    visibility: String,
+    pub owner_id: String,
+    pub path: String,
+    pub name: String,
+    pub logo: String,
+    pub description: Option<String>,
+    pub visibility: String,
     #[serde(rename = "createdAt")]
-    created_at: u64,
+    pub created_at: u64,
 }
 
 pub struct TeamApi<'a> {
@@ -24,15 +26,11 @@ impl<'a> TeamApi<'a> {
         Self { client }
     }
 
-    pub async fn get_list(&self) -> Result<Vec<Team>, Box<dyn std::error::Error>> {
-        let teams = self
-            .client
-            .get("/teams")
-            .send()
-            .await?
-            .json::<Vec<Team>>()
-            .await?;
+    pub async fn get_list(&self) -> Result<Vec<Team>> {
+        self.client.send_json(self.client.get("/v1/teams")).await
+    }
 
-        Ok(teams)
+    pub fn note(&self, team_path: &str) -> NoteApi<'a> {
+        NoteApi::team(self.client, team_path)
     }
 }
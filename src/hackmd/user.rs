@@ -1,7 +1,9 @@
-use serde::{Deserialize, Serialize};
+use serde::Deserialize;
 
-use super::{team::Team, Client};
+use super::{team::Team, Client, Result};
 
+// mirrors the API response, fetching it only checks the token so far
+#[allow(dead_code)]
 #[derive(Debug, Clone, Deserialize)]
 pub struct User {
     id: String,
@@ -22,15 +24,7 @@ impl<'a> UserApi<'a> {
         Self { client }
     }
 
-    pub async fn me(&self) -> Result<User, Box<dyn std::error::Error>> {
-        let resp = self.client.get("/v1/me").send().await?;
-
-        match resp.error_for_status() {
-            Ok(resp) => {
-                let me = resp.json::<User>().await?;
-                Ok(me)
-            }
-            Err(err) => Err(Box::new(err)),
-        }
+    pub async fn me(&self) -> Result<User> {
+        self.client.send_json(self.client.get("/v1/me")).await
     }
 }
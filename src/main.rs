@@ -1,145 +1,310 @@
+mod cli;
+mod creator;
 mod hackmd;
+mod markdown;
+mod merge;
+mod render;
+mod state;
+mod target;
 mod tgdf;
 
 use clap::Parser;
-use futures::future::try_join_all;
-use serde_json::json;
+use cli::{Cli, Command, HackMDArgs, SourceArgs, TemplateArgs};
+use creator::CoWriteCreator;
+use futures::future::join_all;
+use merge::UpdateMode;
+use render::Renderer;
+use state::{Scope, State};
+use std::collections::{BTreeMap, HashSet};
 use std::fs;
-use std::path::PathBuf;
-use tera::Tera;
-use tgdf::{Agenda, Session};
-
-struct CoWriteCreator {
-    client: hackmd::Client,
-    agendas: Vec<Agenda>,
-    category_template: String,
-    note_template: String,
+use std::path::Path;
+use std::time::Duration;
+use target::Target;
+use tgdf::{file as agenda_file, Event, Program, ScrapeReport};
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let cli = Cli::parse();
+    let mut report = ScrapeReport::default();
+    let result = match &cli.command {
+        Command::Scrape {
+            source,
+            output,
+            format,
+        } => scrape(source, output.as_deref(), *format, &mut report).await,
+        Command::Render {
+            source,
+            templates,
+            output_dir,
+        } => render(source, templates, output_dir, &mut report).await,
+        Command::Publish {
+            source,
+            templates,
+            hackmd,
+        } => publish(source, templates, hackmd, None, &mut report).await,
+        Command::Sync {
+            source,
+            templates,
+            hackmd,
+            update_mode,
+        } => publish(source, templates, hackmd, Some(*update_mode), &mut report).await,
+        Command::Clean { hackmd, yes } => clean(hackmd, *yes).await,
+        Command::Status { source, hackmd } => status(source, hackmd, &mut report).await,
+    };
+    if let Some(source) = cli.command.source() {
+        // nothing is scraped when the agenda is read from a file
+        if source.agenda_file.is_none() {
+            eprint!("{}", report);
+        }
+        if let Some(path) = &source.report_json {
+            fs::write(path, serde_json::to_string_pretty(&report)?)?;
+        }
+    }
+    result
 }
 
-impl CoWriteCreator {
-    pub async fn new(token: &str, category_template: String, note_template: String) -> Self {
-        let client = hackmd::Client::new(token).await.unwrap();
+async fn fetch_program(
+    source: &SourceArgs,
+    event: &Event,
+    report: &mut ScrapeReport,
+) -> Result<Program, Box<dyn std::error::Error>> {
+    if let Some(path) = &source.agenda_file {
+        return agenda_file::load(path);
+    }
+    let options = source.scrape_options();
+    let program = match &source.snapshot {
+        Some(dir) => tgdf::fetch(event, &tgdf::Snapshot::new(dir), &options, report).await?,
+        None => {
+            let crawler = tgdf::Crawler::new(
+                source.crawl_concurrency,
+                Duration::from_millis(source.crawl_delay),
+            )?
+            .with_cache(tgdf::HttpCache::new(&source.cache_dir), source.refresh);
+            tgdf::fetch(event, &crawler, &options, report).await?
+        }
+    };
+
+    Ok(program)
+}
 
-        Self {
-            client,
-            category_template,
-            note_template,
-            agendas: vec![],
+/// Log in to HackMD and make sure the team workspace is accessible.
+async fn connect(args: &HackMDArgs) -> Result<Target, Box<dyn std::error::Error>> {
+    let token = fs::read_to_string(&args.token_path)?;
+    let client = hackmd::Client::with_policy(token.trim(), args.request_policy()).await?;
+    if let Some(team) = &args.team {
+        let teams = client.team().get_list().await?;
+        if !teams.iter().any(|t| &t.path == team) {
+            return Err(format!("team {:?} is not found in your HackMD teams", team).into());
         }
     }
 
-    pub fn add_agenda(&mut self, agenda: Agenda) {
-        self.agendas.push(agenda);
-    }
+    Ok(Target::HackMD {
+        client,
+        team: args.team.clone(),
+    })
+}
 
-    fn sessions(&self) -> Vec<&Session> {
-        self.agendas.iter().flat_map(|a| a.sessions()).collect()
+/// The event and workspace the notes of a run are created for.
+fn scope(event: &Event, hackmd: &HackMDArgs) -> Scope {
+    Scope {
+        year: event.year,
+        base_url: event.base_url.to_string(),
+        team: hackmd.team.clone(),
     }
+}
 
-    pub async fn create(&self) -> Result<(), Box<dyn std::error::Error>> {
-        let note_contents = self
-            .sessions()
-            .iter()
-            .map(|session| self.gen_session_note_content(*session))
-            .collect::<Result<Vec<_>, _>>()?;
-        let note_apis = note_contents
-            .iter()
-            .map(|_| self.client.note())
-            .collect::<Vec<_>>();
-        let builders = note_apis
-            .iter()
-            .map(|api| api.builder())
-            .collect::<Vec<_>>();
-        let notes = try_join_all(
-            builders
-                .into_iter()
-                .zip(note_contents)
-                .map(|(builder, content)| builder.content(content).done()),
-        )
-        .await?;
-        let mut notes = notes.into_iter();
-
-        let agendas = self
-            .agendas
-            .iter()
-            .map(|a| {
-                let periods = a
-                    .periods
-                    .iter()
-                    .map(|p| {
-                        let mut sessions = vec![];
-                        for s in &p.sessions {
-                            let mut s = json!(s);
-                            s.as_object_mut()
-                                .unwrap()
-                                .entry("note_id")
-                                .or_insert(json!(notes.next().unwrap().id));
-                            sessions.push(s);
-                        }
-                        let mut p = json!(p);
-                        p.as_object_mut()
-                            .unwrap()
-                            .entry("sessions")
-                            .and_modify(|s| *s = json!(sessions));
-                        p
-                    })
-                    .collect::<Vec<_>>();
-                let mut a = json!(a);
-                a.as_object_mut()
-                    .unwrap()
-                    .entry("periods")
-                    .and_modify(|p| *p = json!(periods));
-                a
-            })
-            .collect::<Vec<_>>();
-
-        // create category
-        let category_content = Tera::one_off(
-            &self.category_template,
-            &tera::Context::from_value(json!({ "agendas": &agendas }))?,
-            false,
-        )?;
-        self.client
-            .note()
-            .builder()
-            .content(category_content)
-            .done()
-            .await?;
-
-        Ok(())
+fn creator(
+    target: Target,
+    event: Event,
+    renderer: Renderer,
+    update_mode: Option<UpdateMode>,
+    program: Program,
+) -> CoWriteCreator {
+    let mut creator = CoWriteCreator::new(target, event, renderer, update_mode);
+    for agenda in program.agendas {
+        creator.add_agenda(agenda);
+    }
+    for (id, speaker) in program.speakers {
+        creator.add_speaker(id, speaker);
     }
+    creator
+}
 
-    pub(crate) fn gen_session_note_content(&self, session: &Session) -> tera::Result<String> {
-        Tera::one_off(
-            &self.note_template,
-            &tera::Context::from_serialize(session)?,
-            false,
-        )
+async fn scrape(
+    source: &SourceArgs,
+    output: Option<&Path>,
+    format: Option<agenda_file::Format>,
+    report: &mut ScrapeReport,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let program = fetch_program(source, &source.event(), report).await?;
+    let format = format
+        .or_else(|| output.map(agenda_file::Format::from_path))
+        .unwrap_or(agenda_file::Format::Json);
+    let content = agenda_file::dump(&program, format)?;
+    match output {
+        Some(path) => fs::write(path, content)?,
+        None => print!("{}", content),
     }
+
+    Ok(())
 }
 
-#[derive(Parser)]
-#[clap(author, version, about, long_about = None)]
-struct Cli {
-    /// Path to the HackMD API token
-    #[clap(long, value_parser, value_name = "FILE")]
-    token_path: PathBuf,
+async fn render(
+    source: &SourceArgs,
+    templates: &TemplateArgs,
+    output_dir: &Path,
+    report: &mut ScrapeReport,
+) -> Result<(), Box<dyn std::error::Error>> {
+    // fail on broken templates before scraping anything
+    let renderer = templates.renderer()?;
+    let event = source.event();
+    let program = fetch_program(source, &event, report).await?;
+    fs::create_dir_all(output_dir)?;
+    let target = Target::Local(output_dir.to_path_buf());
+    let creator = creator(target, event, renderer, None, program);
+
+    creator.create(&mut State::default(), None).await
 }
 
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let cli = Cli::parse();
-    let agendas = tgdf::fetch().await?;
-    let mut creator = CoWriteCreator::new(
-        &fs::read_to_string(&cli.token_path)?,
-        fs::read_to_string("templates/category.tera")?,
-        fs::read_to_string("templates/note.tera")?,
-    )
-    .await;
-
-    for agenda in agendas {
-        creator.add_agenda(agenda);
+/// Create the missing notes on HackMD, and update the existing ones if
+/// `update_mode` is set.
+async fn publish(
+    source: &SourceArgs,
+    templates: &TemplateArgs,
+    hackmd: &HackMDArgs,
+    update_mode: Option<UpdateMode>,
+    report: &mut ScrapeReport,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let renderer = templates.renderer()?;
+    let event = source.event();
+    let mut state = State::load(&hackmd.state)?;
+    state.claim(scope(&event, hackmd))?;
+    let program = fetch_program(source, &event, report).await?;
+    let target = connect(hackmd).await?;
+    let creator = creator(target, event, renderer, update_mode, program);
+
+    let result = creator.create(&mut state, Some(&hackmd.state)).await;
+    state.save(&hackmd.state)?;
+    result
+}
+
+async fn clean(hackmd: &HackMDArgs, yes: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let mut state = State::load(&hackmd.state)?;
+    state.check_team(hackmd.team.as_deref())?;
+    let notes = state.notes();
+    if notes.is_empty() {
+        println!("no notes are recorded in {}", hackmd.state.display());
+        return Ok(());
+    }
+    if !yes {
+        for (key, id) in &notes {
+            println!("{}\t{}", id, key);
+        }
+        println!(
+            "{} notes would be deleted, pass --yes to delete them",
+            notes.len()
+        );
+        return Ok(());
+    }
+
+    let target = connect(hackmd).await?;
+    let results = join_all(notes.iter().map(|(_, id)| target.delete(id))).await;
+    let mut removed = HashSet::new();
+    let mut error = None;
+    for ((key, id), result) in notes.iter().zip(results) {
+        match result {
+            Ok(deleted) => {
+                if !deleted {
+                    eprintln!("note {} of {} is already deleted", id, key);
+                }
+                removed.insert(id.as_str());
+            }
+            Err(err) => {
+                error.get_or_insert(err);
+            }
+        }
+    }
+    state.remove(&removed);
+    state.save(&hackmd.state)?;
+    match error {
+        Some(err) => Err(err),
+        None => Ok(()),
+    }
+}
+
+async fn status(
+    source: &SourceArgs,
+    hackmd: &HackMDArgs,
+    report: &mut ScrapeReport,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let event = source.event();
+    let state = State::load(&hackmd.state)?;
+    state.check_scope(&scope(&event, hackmd))?;
+    let program = fetch_program(source, &event, report).await?;
+    let target = connect(hackmd).await?;
+    let existing = target.note_ids().await?;
+    let describe = |id: Option<&String>| match id {
+        Some(id) if existing.contains(id) => format!("note {}", id),
+        Some(id) => format!("note {} is deleted on HackMD", id),
+        None => "no note".to_string(),
+    };
+
+    let sessions = program.sessions();
+    let mut missing = 0;
+    for session in &sessions {
+        let id = state.sessions.get(&session.key());
+        if id.is_none_or(|id| !existing.contains(id)) {
+            missing += 1;
+        }
+        println!("{}\t{}\t{}", session.slug(), session.title, describe(id));
+    }
+    // speaker, day and track notes are optional, so only the recorded ones
+    // are listed, along with what they are for if it's still on the agenda
+    let dates = program
+        .days()
+        .map(|(day, agenda)| (day, agenda.date.clone()))
+        .collect::<BTreeMap<_, _>>();
+    let tracks = program.tracks();
+    let optional = state
+        .speakers
+        .iter()
+        .map(|(speaker, id)| {
+            let name = program.speakers.get(speaker).map(|p| p.name.clone());
+            (format!("speaker {}", speaker), name, id)
+        })
+        .chain(
+            state
+                .days
+                .iter()
+                .map(|(day, id)| (format!("day {}", day), dates.get(day).cloned(), id)),
+        )
+        .chain(state.tracks.iter().map(|(track, id)| {
+            let name = tracks.contains(track).then(|| format!("T{}", track));
+            (format!("track {}", track), name, id)
+        }));
+    let keys = sessions.iter().map(|s| s.key()).collect::<Vec<_>>();
+    let mut stale = state
+        .sessions
+        .iter()
+        .filter(|(key, _)| !keys.contains(key))
+        .map(|(key, id)| (key.clone(), id))
+        .collect::<Vec<_>>();
+    for (label, name, id) in optional {
+        match name {
+            Some(name) => println!("{}\t{}\t{}", label, name, describe(Some(id))),
+            None => stale.push((label, id)),
+        }
+    }
+    println!("category\t{}", describe(state.category.as_ref()));
+    for (key, id) in &stale {
+        println!("not on the agenda\t{}\t{}", key, describe(Some(id)));
     }
+    println!(
+        "{} sessions, {} without a note, {} notes not on the agenda",
+        sessions.len(),
+        missing,
+        stale.len()
+    );
 
-    creator.create().await
+    Ok(())
 }
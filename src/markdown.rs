@@ -0,0 +1,221 @@
+use ego_tree::NodeRef;
+use reqwest::Url;
+use scraper::{ElementRef, Html, Node};
+
+/// Convert an HTML fragment, e.g. a session description, to Markdown.
+///
+/// Paragraphs, headings, lists, links, emphasis, images, code and quotes are
+/// converted, any other element is replaced by its content. Relative links
+/// and images are resolved against `base`, the page the fragment is from.
+pub fn from_html(html: &str, base: Option<&Url>) -> String {
+    let fragment = Html::parse_fragment(html);
+    squash(&children(*fragment.root_element(), base))
+}
+
+/// Drop the whitespace left between blocks and squash the blank lines.
+fn squash(markdown: &str) -> String {
+    let mut lines: Vec<&str> = vec![];
+    for line in markdown.lines().map(str::trim_end) {
+        let starts_block = lines.last().is_none_or(|l| l.is_empty());
+        if line.is_empty() && starts_block {
+            continue;
+        }
+        // indentation only matters inside lists, which never start a block
+        lines.push(if starts_block {
+            line.trim_start()
+        } else {
+            line
+        });
+    }
+    lines.join("\n").trim().to_string()
+}
+
+fn children(node: NodeRef<Node>, base: Option<&Url>) -> String {
+    node.children().map(|child| convert(child, base)).collect()
+}
+
+fn convert(node: NodeRef<Node>, base: Option<&Url>) -> String {
+    let element = match ElementRef::wrap(node) {
+        Some(element) => element,
+        None => {
+            return match node.value() {
+                Node::Text(text) => collapse_whitespace(text),
+                _ => String::new(),
+            }
+        }
+    };
+    let inner = || children(node, base).trim().to_string();
+    match element.value().name() {
+        "p" | "div" | "section" | "article" => block(&inner()),
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+            let level = element.value().name()[1..].parse().unwrap_or(1);
+            block(&format!("{} {}", "#".repeat(level), inner()))
+        }
+        "br" => "\n".to_string(),
+        "hr" => block("---"),
+        "strong" | "b" => wrap(&inner(), "**"),
+        "em" | "i" => wrap(&inner(), "*"),
+        "del" | "s" => wrap(&inner(), "~~"),
+        "code" => wrap(&inner(), "`"),
+        "a" => match element.value().attr("href") {
+            Some(href) if inner().is_empty() => format!("<{}>", resolve(base, href)),
+            Some(href) => format!("[{}]({})", inner(), resolve(base, href)),
+            None => inner(),
+        },
+        "img" => match element.value().attr("src") {
+            Some(src) => format!(
+                "![{}]({})",
+                element.value().attr("alt").unwrap_or(""),
+                resolve(base, src)
+            ),
+            None => String::new(),
+        },
+        "ul" => list(element, false, base),
+        "ol" => list(element, true, base),
+        "blockquote" => block(&prefix_lines(&squash(&children(node, base)), "> ", "> ")),
+        "pre" => block(&format!(
+            "```\n{}\n```",
+            element.text().collect::<String>().trim_end()
+        )),
+        "script" | "style" => String::new(),
+        _ => children(node, base),
+    }
+}
+
+/// Resolve a relative `href` or `src` against the page, so that it doesn't
+/// point at HackMD once the note is published.
+fn resolve(base: Option<&Url>, link: &str) -> String {
+    match base.and_then(|base| base.join(link).ok()) {
+        Some(url) => url.to_string(),
+        None => link.to_string(),
+    }
+}
+
+fn list(element: ElementRef, ordered: bool, base: Option<&Url>) -> String {
+    let items = element
+        .children()
+        .filter_map(ElementRef::wrap)
+        .filter(|child| child.value().name() == "li")
+        .enumerate()
+        .map(|(i, item)| {
+            let marker = if ordered {
+                format!("{}. ", i + 1)
+            } else {
+                "- ".to_string()
+            };
+            let indent = " ".repeat(marker.len());
+            // keep the list tight, a blank line would turn nested lists into paragraphs
+            let content = squash(&children(*item, base))
+                .lines()
+                .filter(|line| !line.is_empty())
+                .collect::<Vec<_>>()
+                .join("\n");
+            prefix_lines(&content, &marker, &indent)
+        })
+        .collect::<Vec<_>>();
+    block(&items.join("\n"))
+}
+
+fn prefix_lines(text: &str, first: &str, rest: &str) -> String {
+    text.lines()
+        .enumerate()
+        .map(|(i, line)| match (i, line.is_empty()) {
+            (0, _) => format!("{}{}", first, line),
+            (_, true) => rest.trim_end().to_string(),
+            _ => format!("{}{}", rest, line),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn block(content: &str) -> String {
+    format!("\n\n{}\n\n", content)
+}
+
+fn wrap(content: &str, mark: &str) -> String {
+    if content.is_empty() {
+        return String::new();
+    }
+    format!("{}{}{}", mark, content, mark)
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut space = false;
+    for c in text.chars() {
+        if c.is_whitespace() {
+            space = true;
+            continue;
+        }
+        if space {
+            result.push(' ');
+            space = false;
+        }
+        result.push(c);
+    }
+    if space {
+        result.push(' ');
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_html() {
+        let html = r#"
+            <div class="session__description">
+              <p>介紹<strong>即時渲染</strong>的
+                 <a href="https://example.com/slides">投影片</a>。</p>
+              <p>適合<em>程式</em>與美術<br>一起參加</p>
+              <ul>
+                <li>光線追蹤</li>
+                <li>後處理
+                  <ol><li>Bloom</li><li>Tone mapping</li></ol>
+                </li>
+              </ul>
+              <img src="https://example.com/cover.png" alt="封面">
+            </div>
+        "#;
+        assert_eq!(
+            from_html(html, None),
+            "介紹**即時渲染**的 [投影片](https://example.com/slides)。\n\
+             \n\
+             適合*程式*與美術\n\
+             一起參加\n\
+             \n\
+             - 光線追蹤\n\
+             - 後處理\n\
+             \x20 1. Bloom\n\
+             \x20 2. Tone mapping\n\
+             \n\
+             ![封面](https://example.com/cover.png)"
+        );
+    }
+
+    #[test]
+    fn test_resolve_links() {
+        let base = "https://2022.tgdf.tw/speakers/alice"
+            .parse::<Url>()
+            .unwrap();
+        let html = r#"<p><a href="/speakers/bob">Bob</a> <a href="https://example.com/">網站</a>
+            <img src="../images/alice.png" alt="Alice"></p>"#;
+        assert_eq!(
+            from_html(html, Some(&base)),
+            "[Bob](https://2022.tgdf.tw/speakers/bob) [網站](https://example.com/) \
+             ![Alice](https://2022.tgdf.tw/images/alice.png)"
+        );
+        assert_eq!(
+            from_html(r#"<a href="/speakers/bob">Bob</a>"#, None),
+            "[Bob](/speakers/bob)"
+        );
+    }
+
+    #[test]
+    fn test_from_plain_text() {
+        assert_eq!(from_html("  只有文字  ", None), "只有文字");
+        assert_eq!(from_html("", None), "");
+    }
+}
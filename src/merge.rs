@@ -0,0 +1,44 @@
+/// Marks the end of the generated part of a session note, everything below
+/// it is written by attendees and kept when the note is re-synced.
+pub const GENERATED_END: &str = "<!-- co-write:generated-end -->";
+
+/// How existing session notes are updated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum UpdateMode {
+    /// Only replace the generated part above the marker
+    Merge,
+    /// Replace the whole note
+    Overwrite,
+}
+
+/// Replace the generated part of `current` with the one of `generated`.
+///
+/// Returns `None` if either of them lacks the marker, in which case the note
+/// can't be merged safely.
+pub fn merge(current: &str, generated: &str) -> Option<String> {
+    let (header, _) = generated.split_once(GENERATED_END)?;
+    let (_, notes) = current.split_once(GENERATED_END)?;
+    Some(format!("{}{}{}", header, GENERATED_END, notes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge() {
+        let current = format!("# Old\n{}\n# 內容筆記\nnotes\n", GENERATED_END);
+        let generated = format!("# New\n{}\n# 內容筆記\n", GENERATED_END);
+        assert_eq!(
+            merge(&current, &generated).unwrap(),
+            format!("# New\n{}\n# 內容筆記\nnotes\n", GENERATED_END)
+        );
+    }
+
+    #[test]
+    fn test_merge_without_marker() {
+        let generated = format!("# New\n{}\n", GENERATED_END);
+        assert!(merge("# Old\nnotes\n", &generated).is_none());
+        assert!(merge(&generated, "# New\n").is_none());
+    }
+}
@@ -0,0 +1,215 @@
+use crate::markdown;
+use crate::tgdf::{slugify, Session, Time};
+use reqwest::Url;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use tera::{from_value, to_value, Result, Tera};
+
+/// A note other notes can link to.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum NoteKey {
+    /// Keyed by [`Session::key`].
+    Session(String),
+    /// Keyed by the ID of the speaker.
+    Speaker(String),
+    Day(u32),
+    Track(u32),
+    Category,
+}
+
+/// Note IDs filled in while the notes are created.
+pub type NoteIds = Arc<Mutex<HashMap<NoteKey, String>>>;
+
+/// Register the filters and functions every template can use, tracks are
+/// unnamed until [`register_track_names`] is called.
+pub fn register(tera: &mut Tera, note_ids: NoteIds) {
+    tera.register_filter("time_range", time_range);
+    tera.register_filter("duration", duration);
+    tera.register_filter("markdown", to_markdown);
+    tera.register_filter("slugify", slugify_filter);
+    register_track_names(tera, BTreeMap::new());
+    tera.register_function("note_link", move |args: &HashMap<String, Value>| {
+        let key = note_key(args)?;
+        let note_ids = note_ids.lock().unwrap();
+        Ok(to_value(note_link(note_ids.get(&key)))?)
+    });
+}
+
+/// `note_link(session=session)` links to a session note,
+/// `note_link(speaker=speaker)` to a speaker note, where the speaker is either
+/// a presenter or its ID, `note_link(day=1)` and `note_link(track=1)` to the
+/// index notes of a day and a track, and `note_link(category=true)` to the
+/// category note.
+fn note_key(args: &HashMap<String, Value>) -> Result<NoteKey> {
+    if let Some(session) = args.get("session") {
+        let session = from_value::<Session>(session.clone())
+            .map_err(|err| format!("`note_link` expects a session: {}", err))?;
+        return Ok(NoteKey::Session(session.key()));
+    }
+    if let Some(speaker) = args.get("speaker") {
+        let id = match speaker {
+            Value::String(id) => id.as_str(),
+            speaker => speaker
+                .get("id")
+                .and_then(Value::as_str)
+                .ok_or("`note_link` expects a speaker or its ID")?,
+        };
+        return Ok(NoteKey::Speaker(id.to_string()));
+    }
+    let number = |name: &str| match args.get(name) {
+        Some(n) => from_value::<u32>(n.clone())
+            .map(Some)
+            .map_err(|_| tera::Error::msg(format!("`{}` of `note_link` has to be a number", name))),
+        None => Ok(None),
+    };
+    if let Some(day) = number("day")? {
+        return Ok(NoteKey::Day(day));
+    }
+    if let Some(track) = number("track")? {
+        return Ok(NoteKey::Track(track));
+    }
+    if args.contains_key("category") {
+        return Ok(NoteKey::Category);
+    }
+    Err("`note_link` expects a `session`, `speaker`, `day`, `track` or `category` argument".into())
+}
+
+/// Register the `track_name` filter with the names of the tracks.
+pub fn register_track_names(tera: &mut Tera, tracks: BTreeMap<u32, String>) {
+    tera.register_filter(
+        "track_name",
+        move |value: &Value, _: &HashMap<String, Value>| {
+            let track = from_value::<u32>(value.clone())
+                .map_err(|_| "`track_name` expects a track number")?;
+            Ok(to_value(track_name(&tracks, track))?)
+        },
+    );
+}
+
+fn time_pair(filter: &str, value: &Value) -> Result<(Time, Time)> {
+    let (start, end) = from_value::<(String, String)>(value.clone())
+        .map_err(|_| format!("`{}` expects a pair of times", filter))?;
+    let parse = |time: &str| {
+        time.parse::<Time>()
+            .map_err(|_| tera::Error::msg(format!("`{}` got an invalid time {:?}", filter, time)))
+    };
+    Ok((parse(&start)?, parse(&end)?))
+}
+
+/// `["10:00", "10:40"] | time_range` is `10:00 - 10:40`, the separator can be
+/// changed with `sep`.
+fn time_range(value: &Value, args: &HashMap<String, Value>) -> Result<Value> {
+    let (start, end) = time_pair("time_range", value)?;
+    let sep = match args.get("sep") {
+        Some(sep) => from_value::<String>(sep.clone())
+            .map_err(|_| "`sep` of `time_range` has to be a string")?,
+        None => " - ".to_string(),
+    };
+    Ok(to_value(format!("{}{}{}", start, sep, end))?)
+}
+
+/// `["10:00", "10:40"] | duration` is `40`, in minutes.
+fn duration(value: &Value, _: &HashMap<String, Value>) -> Result<Value> {
+    let (start, end) = time_pair("duration", value)?;
+    Ok(to_value(minutes(end) - minutes(start))?)
+}
+
+/// `description_html | markdown(base=url)` is the description in Markdown,
+/// relative links are resolved against `base` if it's given.
+fn to_markdown(value: &Value, args: &HashMap<String, Value>) -> Result<Value> {
+    let html = from_value::<String>(value.clone()).map_err(|_| "`markdown` expects a string")?;
+    let base = match args.get("base") {
+        Some(Value::Null) | None => None,
+        Some(base) => Some(
+            from_value::<String>(base.clone())
+                .ok()
+                .and_then(|base| base.parse::<Url>().ok())
+                .ok_or("`base` of `markdown` has to be a URL")?,
+        ),
+    };
+    Ok(to_value(markdown::from_html(&html, base.as_ref()))?)
+}
+
+fn slugify_filter(value: &Value, _: &HashMap<String, Value>) -> Result<Value> {
+    let text = match value {
+        Value::String(text) => text.clone(),
+        value => value.to_string(),
+    };
+    Ok(to_value(slugify(&text))?)
+}
+
+fn minutes(time: Time) -> i64 {
+    time.hour as i64 * 60 + time.minute as i64
+}
+
+pub fn track_name(tracks: &BTreeMap<u32, String>, track: u32) -> String {
+    match tracks.get(&track) {
+        Some(name) => name.clone(),
+        None => format!("T{}", track),
+    }
+}
+
+/// Link to a note from another note, empty if the note isn't created yet.
+pub fn note_link(id: Option<&String>) -> String {
+    match id {
+        Some(id) => format!("/s/{}", id),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tera::Context;
+
+    #[test]
+    fn test_filters() {
+        let note_ids = NoteIds::default();
+        note_ids.lock().unwrap().extend([
+            (
+                NoteKey::Session("https://2022.tgdf.tw/agenda/rendering".into()),
+                "abc".into(),
+            ),
+            (NoteKey::Speaker("alice".into()), "def".into()),
+            (NoteKey::Day(1), "d1".into()),
+            (NoteKey::Track(2), "t2".into()),
+            (NoteKey::Category, "c".into()),
+        ]);
+        let mut tera = Tera::default();
+        register(&mut tera, note_ids);
+        register_track_names(&mut tera, BTreeMap::from([(1, "主舞台".to_string())]));
+        tera.add_raw_template(
+            "note",
+            "{{ time | time_range }}|{{ time | time_range(sep=\"-\") }}|{{ time | duration }}|\
+             {{ title | slugify }}|{{ 1 | track_name }}/{{ 2 | track_name }}|\
+             {{ note_link(session=session) }}|{{ note_link(session=other) }}|\
+             {{ note_link(speaker=speaker) }}|{{ note_link(speaker=\"alice\") }}|\
+             {{ note_link(speaker=\"bob\") }}|{{ note_link(day=1) }}|{{ note_link(track=2) }}|\
+             {{ note_link(track=1) }}|{{ note_link(category=true) }}|\
+             {{ link | markdown(base=session.url) }}|{{ link | markdown }}",
+        )
+        .unwrap();
+
+        let session = Session::test(1, 1, ("9:30", "10:10"), "即時渲染 Real-time Rendering!")
+            .with_url("https://2022.tgdf.tw/agenda/rendering");
+        let session = json!(session);
+        let mut other = session.clone();
+        other["url"] = json!(null);
+        let context = Context::from_value(json!({
+            "time": session["time"],
+            "title": session["title"],
+            "session": session,
+            "other": other,
+            "speaker": { "id": "alice", "name": "Alice" },
+            "link": "<a href=\"/speakers/alice\">Alice</a>",
+        }))
+        .unwrap();
+        assert_eq!(
+            tera.render("note", &context).unwrap(),
+            "9:30 - 10:10|9:30-10:10|40|即時渲染-real-time-rendering|主舞台/T2|/s/abc||/s/def|/s/def||/s/d1|/s/t2||/s/c|\
+             [Alice](https://2022.tgdf.tw/speakers/alice)|[Alice](/speakers/alice)"
+        );
+    }
+}
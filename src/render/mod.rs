@@ -0,0 +1,326 @@
+mod filters;
+mod rules;
+
+use crate::tgdf::{Event, Presenter, Session};
+use filters::NoteIds;
+pub use filters::NoteKey;
+use rules::Rule;
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use tera::{Context, Tera};
+
+/// Names of the title and permalink patterns among the templates.
+const TITLE: &str = "title pattern";
+const PERMALINK: &str = "permalink pattern";
+
+/// Every template of a template directory, loaded into one [`Tera`] instance so
+/// that templates can `include`, `extend` and `import` each other.
+pub struct Renderer {
+    tera: Tera,
+    dir: PathBuf,
+    category: String,
+    note: String,
+    /// Templates of the notes that are only created if their template is set.
+    extra: HashMap<ExtraNote, String>,
+    /// Pick the note template of a session, falling back to `note`.
+    rules: Vec<Rule>,
+    /// Shared with the `note_link` function.
+    note_ids: NoteIds,
+}
+
+impl Renderer {
+    /// Load and parse every `*.tera` file under `dir`.
+    ///
+    /// `category` and `note` are the names of the category and session note
+    /// templates relative to `dir`. Syntax errors, missing parents or macro
+    /// files and missing templates are all reported here, before anything is
+    /// scraped or published.
+    pub fn new(dir: &Path, category: &str, note: &str) -> Result<Self, Box<dyn Error>> {
+        let glob = dir.join("**").join("*.tera");
+        let mut tera = Tera::new(&glob.to_string_lossy()).map_err(|err| {
+            format!(
+                "failed to load templates from {}: {}",
+                dir.display(),
+                error_chain(&err)
+            )
+        })?;
+        let note_ids = NoteIds::default();
+        filters::register(&mut tera, note_ids.clone());
+        let renderer = Self {
+            tera,
+            dir: dir.to_path_buf(),
+            category: category.to_string(),
+            note: note.to_string(),
+            extra: HashMap::new(),
+            rules: vec![],
+            note_ids,
+        };
+        renderer.check(category)?;
+        renderer.check(note)?;
+
+        Ok(renderer)
+    }
+
+    /// Load the note template rules, the track names and the title and
+    /// permalink patterns from `name` relative to the template directory, a
+    /// missing file has none of them.
+    pub fn with_rules(mut self, name: &Path) -> Result<Self, Box<dyn Error>> {
+        let path = self.dir.join(name);
+        let file = rules::load(&path)?;
+        for rule in &file.rules {
+            self.check(&rule.template)?;
+        }
+        for (name, pattern) in [(TITLE, &file.title), (PERMALINK, &file.permalink)] {
+            if let Some(pattern) = pattern {
+                self.tera.add_raw_template(name, pattern).map_err(|err| {
+                    format!(
+                        "invalid {} in {}: {}",
+                        name,
+                        path.display(),
+                        error_chain(&err)
+                    )
+                })?;
+            }
+        }
+        self.rules = file.rules;
+        filters::register_track_names(&mut self.tera, file.tracks);
+
+        Ok(self)
+    }
+
+    /// Also render the notes of `kind` from the template `name`.
+    pub fn with_extra_template(
+        mut self,
+        kind: ExtraNote,
+        name: &str,
+    ) -> Result<Self, Box<dyn Error>> {
+        self.check(name)?;
+        self.extra.insert(kind, name.to_string());
+        Ok(self)
+    }
+
+    /// Whether the notes of `kind` should be created.
+    pub fn renders(&self, kind: ExtraNote) -> bool {
+        self.extra.contains_key(&kind)
+    }
+
+    /// Make a note available to `note_link`.
+    pub fn set_note_id(&self, key: NoteKey, id: String) {
+        self.note_ids.lock().unwrap().insert(key, id);
+    }
+
+    fn check(&self, name: &str) -> Result<(), Box<dyn Error>> {
+        if !self.tera.get_template_names().any(|n| n == name) {
+            return Err(format!("template {} is not found in {}", name, self.dir.display()).into());
+        }
+        Ok(())
+    }
+
+    /// Name of the template a session note is rendered from.
+    pub fn note_template(&self, session: &Session) -> &str {
+        rules::select(&self.rules, session).unwrap_or(&self.note)
+    }
+
+    /// Render the note of a session, its `presenters` are resolved from the
+    /// speaker IDs by the caller.
+    pub fn session(
+        &self,
+        session: &Session,
+        presenters: &[&Presenter],
+        event: &Event,
+    ) -> Result<String, Box<dyn Error>> {
+        let context = Self::session_context(session, presenters, event)?;
+        self.render(self.note_template(session), &context)
+    }
+
+    /// Render the title of a session note, if a title pattern is configured.
+    pub fn session_title(
+        &self,
+        session: &Session,
+        presenters: &[&Presenter],
+        event: &Event,
+    ) -> Result<Option<String>, Box<dyn Error>> {
+        self.pattern(TITLE, session, presenters, event)
+    }
+
+    /// Render the permalink of a session note, if a permalink pattern is
+    /// configured.
+    pub fn session_permalink(
+        &self,
+        session: &Session,
+        presenters: &[&Presenter],
+        event: &Event,
+    ) -> Result<Option<String>, Box<dyn Error>> {
+        self.pattern(PERMALINK, session, presenters, event)
+    }
+
+    fn pattern(
+        &self,
+        name: &str,
+        session: &Session,
+        presenters: &[&Presenter],
+        event: &Event,
+    ) -> Result<Option<String>, Box<dyn Error>> {
+        if !self.tera.get_template_names().any(|n| n == name) {
+            return Ok(None);
+        }
+        let context = Self::session_context(session, presenters, event)?;
+        match self.render(name, &context)?.trim() {
+            "" => Err(format!("{} of {:?} is empty", name, session.title).into()),
+            value => Ok(Some(value.to_string())),
+        }
+    }
+
+    fn session_context(
+        session: &Session,
+        presenters: &[&Presenter],
+        event: &Event,
+    ) -> Result<Context, Box<dyn Error>> {
+        let mut context = Context::from_serialize(session)?;
+        context.insert("presenters", presenters);
+        context.insert("event", event);
+        Ok(context)
+    }
+
+    pub fn category(&self, context: &Context) -> Result<String, Box<dyn Error>> {
+        self.render(&self.category, context)
+    }
+
+    pub fn extra(&self, kind: ExtraNote, context: &Context) -> Result<String, Box<dyn Error>> {
+        match self.extra.get(&kind) {
+            Some(name) => self.render(name, context),
+            None => Err(format!("no template of {:?} notes is set", kind).into()),
+        }
+    }
+
+    fn render(&self, name: &str, context: &Context) -> Result<String, Box<dyn Error>> {
+        self.tera
+            .render(name, context)
+            .map_err(|err| error_chain(&err).into())
+    }
+}
+
+/// Notes that are only created if their template is set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ExtraNote {
+    /// A note per speaker, linking to their session notes.
+    Speaker,
+    /// An index note per day.
+    Day,
+    /// An index note per track, covering every day.
+    Track,
+}
+
+/// Tera keeps the useful part of its errors, e.g. the location of a syntax
+/// error, in the error source, so join the whole chain into one message.
+fn error_chain(err: &tera::Error) -> String {
+    let mut message = err.to_string();
+    let mut source = err.source();
+    while let Some(err) = source {
+        message.push_str(": ");
+        message.push_str(&err.to_string());
+        source = err.source();
+    }
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_default_templates() {
+        Renderer::new(Path::new("templates"), "category.tera", "note.tera")
+            .unwrap()
+            .with_rules(Path::new("rules.yaml"))
+            .unwrap()
+            .with_extra_template(ExtraNote::Speaker, "speaker.tera")
+            .unwrap()
+            .with_extra_template(ExtraNote::Day, "day.tera")
+            .unwrap()
+            .with_extra_template(ExtraNote::Track, "track.tera")
+            .unwrap();
+    }
+
+    #[test]
+    fn test_invalid_templates() {
+        let dir = std::env::temp_dir().join(format!("co-write-render-{}", std::process::id()));
+        fs::create_dir_all(dir.join("partials")).unwrap();
+        fs::write(
+            dir.join("category.tera"),
+            "{% include \"partials/tags.tera\" %}",
+        )
+        .unwrap();
+        fs::write(dir.join("partials/tags.tera"), "{{ event.year }}TGDF").unwrap();
+
+        let err = Renderer::new(&dir, "category.tera", "note.tera")
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("note.tera is not found"));
+
+        fs::write(dir.join("note.tera"), "{% if title %}").unwrap();
+        let err = Renderer::new(&dir, "category.tera", "note.tera")
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("note.tera"));
+
+        fs::write(dir.join("note.tera"), "# {{ title }}").unwrap();
+        let renderer = Renderer::new(&dir, "category.tera", "note.tera").unwrap();
+        let context =
+            Context::from_value(serde_json::json!({ "event": { "year": 2023 } })).unwrap();
+        assert_eq!(renderer.category(&context).unwrap(), "2023TGDF");
+        let err = renderer
+            .with_extra_template(ExtraNote::Speaker, "speaker.tera")
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("speaker.tera is not found"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_patterns() {
+        let dir = std::env::temp_dir().join(format!("co-write-patterns-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("category.tera"), "").unwrap();
+        fs::write(dir.join("note.tera"), "").unwrap();
+        let new = || {
+            Renderer::new(&dir, "category.tera", "note.tera")?.with_rules(Path::new("rules.yaml"))
+        };
+
+        let session = Session::test(1, 2, ("10:30", "11:10"), "關卡設計實戰")
+            .with_url("https://2022.tgdf.tw/agenda/level-design");
+        let event = Event::new(2022);
+        let renderer = new().unwrap();
+        assert_eq!(renderer.session_title(&session, &[], &event).unwrap(), None);
+        assert_eq!(
+            renderer.session_permalink(&session, &[], &event).unwrap(),
+            None
+        );
+
+        fs::write(
+            dir.join("rules.yaml"),
+            "title: \"{{ event.year }} TGDF {{ title }}\"\n\
+             permalink: \"tgdf{{ event.year }}-d{{ day }}-t{{ track }}-\
+             {{ time.0 | replace(from=':', to='') }}\"\n",
+        )
+        .unwrap();
+        let renderer = new().unwrap();
+        assert_eq!(
+            renderer.session_title(&session, &[], &event).unwrap(),
+            Some("2022 TGDF 關卡設計實戰".to_string())
+        );
+        assert_eq!(
+            renderer.session_permalink(&session, &[], &event).unwrap(),
+            Some("tgdf2022-d1-t2-1030".to_string())
+        );
+
+        fs::write(dir.join("rules.yaml"), "permalink: \"{{ day \"\n").unwrap();
+        let err = new().err().unwrap();
+        assert!(err.to_string().contains("invalid permalink pattern"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,108 @@
+use crate::tgdf::{Session, SessionKind};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+/// Picks the note template of the sessions it matches.
+///
+/// Every condition that is set has to match, a rule without conditions
+/// matches every session.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct Rule {
+    /// Template name relative to the template directory.
+    pub template: String,
+    /// Matches sessions having any of these tags.
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub track: Option<u32>,
+    pub day: Option<u32>,
+    pub kind: Option<SessionKind>,
+}
+
+impl Rule {
+    pub fn matches(&self, session: &Session) -> bool {
+        (self.tags.is_empty() || self.tags.iter().any(|t| session.tags.contains(t)))
+            && self.track.is_none_or(|track| track == session.track)
+            && self.day.is_none_or(|day| day == session.day)
+            && self.kind.is_none_or(|kind| kind == session.kind)
+    }
+}
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct RuleFile {
+    #[serde(default)]
+    pub rules: Vec<Rule>,
+    /// Names of the tracks, shown by the `track_name` filter.
+    #[serde(default)]
+    pub tracks: BTreeMap<u32, String>,
+    /// Pattern of the session note titles, rendered from the same fields as
+    /// the note templates.
+    #[serde(default)]
+    pub title: Option<String>,
+    /// Pattern of the session note permalinks, e.g. `tgdf2022-d1-t2-1030`.
+    #[serde(default)]
+    pub permalink: Option<String>,
+}
+
+/// Load the rules from a YAML or JSON file, a missing file has no rules.
+pub fn load(path: &Path) -> Result<RuleFile, Box<dyn Error>> {
+    if !path.exists() {
+        return Ok(RuleFile::default());
+    }
+    let content = fs::read_to_string(path)?;
+    let file = serde_yaml::from_str::<RuleFile>(&content)
+        .map_err(|err| format!("invalid template rules in {}: {}", path.display(), err))?;
+    Ok(file)
+}
+
+/// The template of the first rule matching `session`.
+pub fn select<'a>(rules: &'a [Rule], session: &Session) -> Option<&'a str> {
+    rules
+        .iter()
+        .find(|rule| rule.matches(session))
+        .map(|rule| rule.template.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(day: u32, track: u32, tags: &[&str], kind: SessionKind) -> Session {
+        Session::test(day, track, ("10:00", "10:40"), "title")
+            .with_tags(tags)
+            .with_kind(kind)
+    }
+
+    #[test]
+    fn test_select() {
+        let rules = serde_yaml::from_str::<RuleFile>(
+            r#"
+rules:
+  - template: workshop.tera
+    tags: [工作坊, Workshop]
+  - template: keynote.tera
+    day: 1
+    track: 1
+  - template: panel.tera
+    kind: panel
+"#,
+        )
+        .unwrap()
+        .rules;
+
+        let workshop = session(2, 3, &["美術", "工作坊"], SessionKind::Talk);
+        assert_eq!(select(&rules, &workshop), Some("workshop.tera"));
+        let keynote = session(1, 1, &[], SessionKind::Talk);
+        assert_eq!(select(&rules, &keynote), Some("keynote.tera"));
+        let panel = session(1, 1, &[], SessionKind::Panel);
+        assert_eq!(select(&rules, &panel), Some("keynote.tera"));
+        let panel = session(2, 1, &[], SessionKind::Panel);
+        assert_eq!(select(&rules, &panel), Some("panel.tera"));
+        let talk = session(1, 2, &["程式"], SessionKind::Talk);
+        assert_eq!(select(&rules, &talk), None);
+    }
+}
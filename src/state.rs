@@ -0,0 +1,220 @@
+use crate::render::NoteKey;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+/// The event and the workspace the notes of a state file are created for.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Scope {
+    pub year: u32,
+    pub base_url: String,
+    /// Path of the team workspace, `None` for the personal workspace.
+    pub team: Option<String>,
+}
+
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "TGDF {} from {}", self.year, self.base_url)?;
+        match &self.team {
+            Some(team) => write!(f, " in team {}", team),
+            None => write!(f, " in the personal workspace"),
+        }
+    }
+}
+
+/// Notes created by previous runs, persisted so that a re-run updates them
+/// instead of creating duplicates.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct State {
+    /// Set by the first run, the state file can't be reused for another
+    /// event or workspace.
+    #[serde(default)]
+    pub scope: Option<Scope>,
+    /// Session note IDs keyed by [`crate::tgdf::Session::key`].
+    #[serde(default)]
+    pub sessions: BTreeMap<String, String>,
+    /// Speaker note IDs keyed by [`crate::tgdf::Presenter::id`].
+    #[serde(default)]
+    pub speakers: BTreeMap<String, String>,
+    /// Day index note IDs keyed by the day, starting from 1.
+    #[serde(default)]
+    pub days: BTreeMap<u32, String>,
+    /// Track index note IDs keyed by the track.
+    #[serde(default)]
+    pub tracks: BTreeMap<u32, String>,
+    #[serde(default)]
+    pub category: Option<String>,
+}
+
+impl State {
+    /// Load the state file, a missing file is an empty state.
+    pub fn load(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    /// Refuse to use a state recorded for another event or workspace, and
+    /// claim a new state for `scope`.
+    pub fn claim(&mut self, scope: Scope) -> Result<(), Box<dyn std::error::Error>> {
+        self.check_scope(&scope)?;
+        self.scope = Some(scope);
+        Ok(())
+    }
+
+    /// Refuse to use a state recorded for another event or workspace.
+    pub fn check_scope(&self, scope: &Scope) -> Result<(), Box<dyn std::error::Error>> {
+        match &self.scope {
+            Some(recorded) if recorded != scope => Err(format!(
+                "the notes in the state file are created for {}, not {}, pass another --state",
+                recorded, scope
+            )
+            .into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Refuse to use a state recorded for another workspace.
+    pub fn check_team(&self, team: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
+        match &self.scope {
+            Some(recorded) if recorded.team.as_deref() != team => Err(format!(
+                "the notes in the state file are created {}, pass the same --team",
+                match &recorded.team {
+                    Some(team) => format!("in team {}", team),
+                    None => "in the personal workspace".to_string(),
+                }
+            )
+            .into()),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn note_id(&self, key: &NoteKey) -> Option<&String> {
+        match key {
+            NoteKey::Session(key) => self.sessions.get(key),
+            NoteKey::Speaker(id) => self.speakers.get(id),
+            NoteKey::Day(day) => self.days.get(day),
+            NoteKey::Track(track) => self.tracks.get(track),
+            NoteKey::Category => self.category.as_ref(),
+        }
+    }
+
+    pub fn set_note_id(&mut self, key: NoteKey, id: String) {
+        match key {
+            NoteKey::Session(key) => self.sessions.insert(key, id),
+            NoteKey::Speaker(speaker) => self.speakers.insert(speaker, id),
+            NoteKey::Day(day) => self.days.insert(day, id),
+            NoteKey::Track(track) => self.tracks.insert(track, id),
+            NoteKey::Category => self.category.replace(id),
+        };
+    }
+
+    /// Every recorded note as `(what it is for, note ID)`.
+    pub fn notes(&self) -> Vec<(String, String)> {
+        let sessions = self.sessions.iter().map(|(k, id)| (k.clone(), id.clone()));
+        let speakers = self
+            .speakers
+            .iter()
+            .map(|(speaker, id)| (format!("speaker {}", speaker), id.clone()));
+        let days = self
+            .days
+            .iter()
+            .map(|(day, id)| (format!("day {}", day), id.clone()));
+        let tracks = self
+            .tracks
+            .iter()
+            .map(|(track, id)| (format!("track {}", track), id.clone()));
+        let category = self
+            .category
+            .iter()
+            .map(|id| ("category".into(), id.clone()));
+        sessions
+            .chain(speakers)
+            .chain(days)
+            .chain(tracks)
+            .chain(category)
+            .collect()
+    }
+
+    /// Forget the notes `ids`, e.g. after they are deleted.
+    pub fn remove(&mut self, ids: &HashSet<&str>) {
+        self.sessions.retain(|_, id| !ids.contains(id.as_str()));
+        self.speakers.retain(|_, id| !ids.contains(id.as_str()));
+        self.days.retain(|_, id| !ids.contains(id.as_str()));
+        self.tracks.retain(|_, id| !ids.contains(id.as_str()));
+        if self.category.as_deref().is_some_and(|id| ids.contains(id)) {
+            self.category = None;
+        }
+    }
+
+    /// Save the state, replacing the file at once so that an interrupted save
+    /// leaves the previous state behind.
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        fs::write(&tmp, serde_json::to_string_pretty(self)?)?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_missing_fields() {
+        let state = serde_json::from_str::<State>("{}").unwrap();
+        assert!(state.sessions.is_empty());
+        assert!(state.speakers.is_empty());
+        assert!(state.days.is_empty() && state.tracks.is_empty());
+        assert!(state.category.is_none());
+        assert!(state.scope.is_none());
+    }
+
+    #[test]
+    fn test_scope() {
+        let scope = |year: u32, team: Option<&str>| Scope {
+            year,
+            base_url: format!("https://{}.tgdf.tw/", year),
+            team: team.map(str::to_string),
+        };
+        let mut state = State::default();
+        state.claim(scope(2022, None)).unwrap();
+        state.claim(scope(2022, None)).unwrap();
+        state.check_team(None).unwrap();
+
+        let err = state.check_scope(&scope(2023, None)).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "the notes in the state file are created for TGDF 2022 from \
+             https://2022.tgdf.tw/ in the personal workspace, not TGDF 2023 from \
+             https://2023.tgdf.tw/ in the personal workspace, pass another --state"
+        );
+        assert!(state.claim(scope(2022, Some("tgdf"))).is_err());
+        assert!(state.check_team(Some("tgdf")).is_err());
+        assert_eq!(state.scope, Some(scope(2022, None)));
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut state = State::default();
+        state.set_note_id(NoteKey::Session("rendering".into()), "a".into());
+        state.set_note_id(NoteKey::Speaker("alice".into()), "b".into());
+        state.set_note_id(NoteKey::Day(1), "d".into());
+        state.set_note_id(NoteKey::Category, "c".into());
+        assert_eq!(state.notes().len(), 4);
+        let json = serde_json::to_string(&state).unwrap();
+        assert_eq!(serde_json::from_str::<State>(&json).unwrap().days[&1], "d");
+
+        state.remove(&HashSet::from(["b", "c", "d"]));
+        assert_eq!(
+            state.notes(),
+            vec![("rendering".to_string(), "a".to_string())]
+        );
+    }
+}
@@ -0,0 +1,136 @@
+use crate::hackmd;
+use std::collections::HashSet;
+use std::io;
+use std::path::PathBuf;
+
+/// Where the rendered notes go.
+pub enum Target {
+    /// Publish the notes on HackMD, under the team workspace if `team` is set.
+    HackMD {
+        client: hackmd::Client,
+        team: Option<String>,
+    },
+    /// Write the notes into a directory as Markdown files without touching HackMD.
+    Local(PathBuf),
+}
+
+/// How a new note is named.
+pub struct NewNote {
+    /// Identifies the note locally, it is used as both the file name and the
+    /// placeholder note ID when writing to a directory.
+    pub name: String,
+    pub title: Option<String>,
+    /// Short URL of the note on HackMD, which replaces `name` when writing to
+    /// a directory.
+    pub permalink: Option<String>,
+}
+
+impl Target {
+    fn notes<'a>(client: &'a hackmd::Client, team: &Option<String>) -> hackmd::NoteApi<'a> {
+        match team {
+            Some(team) => client.team().note(team),
+            None => client.note(),
+        }
+    }
+
+    /// Create a note and return its ID.
+    pub async fn create(
+        &self,
+        note: &NewNote,
+        content: String,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        match self {
+            Self::HackMD { client, team } => {
+                let mut builder = Self::notes(client, team).builder().content(content);
+                if let Some(title) = &note.title {
+                    builder = builder.title(title.clone());
+                }
+                if let Some(permalink) = &note.permalink {
+                    builder = builder.permalink(permalink.clone());
+                }
+                Ok(builder.done().await?.id)
+            }
+            Self::Local(dir) => {
+                let name = note.permalink.as_ref().unwrap_or(&note.name);
+                tokio::fs::write(dir.join(format!("{}.md", name)), content).await?;
+                Ok(name.clone())
+            }
+        }
+    }
+
+    /// Read the content of an existing note.
+    pub async fn content(&self, id: &str) -> Result<String, Box<dyn std::error::Error>> {
+        match self {
+            Self::HackMD { client, team } => {
+                let note = Self::notes(client, team).get(id).await?;
+                Ok(note.content)
+            }
+            Self::Local(dir) => {
+                Ok(tokio::fs::read_to_string(dir.join(format!("{}.md", id))).await?)
+            }
+        }
+    }
+
+    /// Replace the content of an existing note.
+    pub async fn update(
+        &self,
+        id: &str,
+        content: String,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        match self {
+            Self::HackMD { client, team } => {
+                Self::notes(client, team)
+                    .update(id.to_string())
+                    .content(content)
+                    .done()
+                    .await?;
+                Ok(())
+            }
+            Self::Local(dir) => {
+                tokio::fs::write(dir.join(format!("{}.md", id)), content).await?;
+                Ok(())
+            }
+        }
+    }
+
+    /// Delete a note, returning `false` if it was already gone.
+    pub async fn delete(&self, id: &str) -> Result<bool, Box<dyn std::error::Error>> {
+        match self {
+            Self::HackMD { client, team } => match Self::notes(client, team).delete(id).await {
+                Ok(()) => Ok(true),
+                Err(hackmd::Error::NotFound) => Ok(false),
+                Err(err) => Err(err.into()),
+            },
+            Self::Local(dir) => {
+                match tokio::fs::remove_file(dir.join(format!("{}.md", id))).await {
+                    Ok(()) => Ok(true),
+                    Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(false),
+                    Err(err) => Err(err.into()),
+                }
+            }
+        }
+    }
+
+    /// IDs of every note in the workspace.
+    pub async fn note_ids(&self) -> Result<HashSet<String>, Box<dyn std::error::Error>> {
+        match self {
+            Self::HackMD { client, team } => {
+                let notes = Self::notes(client, team).get_list().await?;
+                Ok(notes.into_iter().map(|note| note.id).collect())
+            }
+            Self::Local(dir) => {
+                let mut ids = HashSet::new();
+                let mut entries = tokio::fs::read_dir(dir).await?;
+                while let Some(entry) = entries.next_entry().await? {
+                    let path = entry.path();
+                    if path.extension().is_some_and(|ext| ext == "md") {
+                        if let Some(stem) = path.file_stem() {
+                            ids.insert(stem.to_string_lossy().into_owned());
+                        }
+                    }
+                }
+                Ok(ids)
+            }
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// Validators of a cached page, used to revalidate it with the site.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Validators {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Entry {
+    url: String,
+    #[serde(flatten)]
+    validators: Validators,
+}
+
+/// Pages downloaded by previous runs, stored on disk keyed by URL.
+#[derive(Debug, Clone)]
+pub struct HttpCache {
+    dir: PathBuf,
+}
+
+impl HttpCache {
+    pub fn new<P: Into<PathBuf>>(dir: P) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// File name stem of a URL, a FNV-1a hash which is stable across builds.
+    fn key(url: &Url) -> String {
+        let hash = url.as_str().bytes().fold(0xcbf29ce484222325u64, |h, b| {
+            (h ^ b as u64).wrapping_mul(0x100000001b3)
+        });
+        format!("{:016x}", hash)
+    }
+
+    /// The cached page of `url` and its validators, if there is one.
+    pub async fn get(&self, url: &Url) -> Option<(Validators, String)> {
+        let key = Self::key(url);
+        let entry = tokio::fs::read_to_string(self.dir.join(format!("{}.json", key)))
+            .await
+            .ok()?;
+        let entry = serde_json::from_str::<Entry>(&entry).ok()?;
+        // guard against hash collisions
+        if entry.url != url.as_str() {
+            return None;
+        }
+        let content = tokio::fs::read_to_string(self.dir.join(format!("{}.html", key)))
+            .await
+            .ok()?;
+
+        Some((entry.validators, content))
+    }
+
+    pub async fn put(
+        &self,
+        url: &Url,
+        validators: Validators,
+        content: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let key = Self::key(url);
+        let entry = Entry {
+            url: url.to_string(),
+            validators,
+        };
+        tokio::fs::create_dir_all(&self.dir).await?;
+        tokio::fs::write(self.dir.join(format!("{}.html", key)), content).await?;
+        tokio::fs::write(
+            self.dir.join(format!("{}.json", key)),
+            serde_json::to_string_pretty(&entry)?,
+        )
+        .await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_round_trip() {
+        let dir = std::env::temp_dir().join(format!("tgdf-cache-test-{}", std::process::id()));
+        let cache = HttpCache::new(&dir);
+        let url = "https://2022.tgdf.tw/agenda".parse().unwrap();
+        assert!(cache.get(&url).await.is_none());
+
+        let validators = Validators {
+            etag: Some("\"abc\"".to_string()),
+            last_modified: None,
+        };
+        cache.put(&url, validators, "<html></html>").await.unwrap();
+        let (validators, content) = cache.get(&url).await.unwrap();
+        assert_eq!(validators.etag.as_deref(), Some("\"abc\""));
+        assert_eq!(content, "<html></html>");
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
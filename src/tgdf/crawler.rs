@@ -0,0 +1,157 @@
+use super::{
+    cache::{HttpCache, Validators},
+    PageSource,
+};
+use reqwest::{
+    header::{ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED},
+    StatusCode, Url,
+};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::{
+    sync::{OnceCell, Semaphore},
+    time::Instant,
+};
+
+const USER_AGENT: &str = concat!(
+    env!("CARGO_PKG_NAME"),
+    "/",
+    env!("CARGO_PKG_VERSION"),
+    " (+https://github.com/Bogay/create-co-write-for-tgdf)"
+);
+
+/// Fetch pages from the live site politely.
+///
+/// At most `concurrency` requests are in flight, requests to the same host
+/// are spaced by `delay`, and every URL is downloaded at most once per run.
+/// With a cache, pages are revalidated instead of downloaded again, and the
+/// cached copy is used when the site is unreachable.
+#[derive(Debug)]
+pub struct Crawler {
+    client: reqwest::Client,
+    permits: Semaphore,
+    delay: Duration,
+    /// When the next request to each host may be sent.
+    next_slot: Mutex<HashMap<String, Instant>>,
+    pages: Mutex<HashMap<Url, Arc<OnceCell<String>>>>,
+    cache: Option<HttpCache>,
+    /// Ignore cached pages and download everything again.
+    refresh: bool,
+}
+
+impl Crawler {
+    pub fn new(concurrency: usize, delay: Duration) -> Result<Self, Box<dyn std::error::Error>> {
+        let client = reqwest::Client::builder().user_agent(USER_AGENT).build()?;
+        Ok(Self {
+            client,
+            permits: Semaphore::new(concurrency.max(1)),
+            delay,
+            next_slot: Mutex::new(HashMap::new()),
+            pages: Mutex::new(HashMap::new()),
+            cache: None,
+            refresh: false,
+        })
+    }
+
+    pub fn with_cache(mut self, cache: HttpCache, refresh: bool) -> Self {
+        self.cache = Some(cache);
+        self.refresh = refresh;
+        self
+    }
+
+    /// Reserve the next request slot of the URL's host.
+    fn reserve_slot(&self, url: &Url) -> Instant {
+        let host = url.host_str().unwrap_or_default().to_string();
+        let now = Instant::now();
+        let mut next_slot = self.next_slot.lock().unwrap();
+        let slot = next_slot.get(&host).map_or(now, |&t| t.max(now));
+        next_slot.insert(host, slot + self.delay);
+        slot
+    }
+
+    async fn download(&self, url: &Url) -> Result<String, Box<dyn std::error::Error>> {
+        let cached = match &self.cache {
+            Some(cache) if !self.refresh => cache.get(url).await,
+            _ => None,
+        };
+        let _permit = self.permits.acquire().await?;
+        tokio::time::sleep_until(self.reserve_slot(url)).await;
+        let mut request = self.client.get(url.clone());
+        if let Some((validators, _)) = &cached {
+            if let Some(etag) = &validators.etag {
+                request = request.header(IF_NONE_MATCH, etag);
+            }
+            if let Some(last_modified) = &validators.last_modified {
+                request = request.header(IF_MODIFIED_SINCE, last_modified);
+            }
+        }
+        let response = match request.send().await {
+            Ok(response) if !response.status().is_server_error() => response,
+            result => match cached {
+                Some((_, content)) => {
+                    eprintln!("{} is unreachable, use the cached copy", url);
+                    return Ok(content);
+                }
+                None => result?.error_for_status()?,
+            },
+        };
+        if response.status() == StatusCode::NOT_MODIFIED {
+            if let Some((_, content)) = cached {
+                return Ok(content);
+            }
+        }
+        let response = response.error_for_status()?;
+        let header = |name| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(String::from)
+        };
+        let validators = Validators {
+            etag: header(ETAG),
+            last_modified: header(LAST_MODIFIED),
+        };
+        let content = response.text().await?;
+        if let Some(cache) = &self.cache {
+            cache.put(url, validators, &content).await?;
+        }
+
+        Ok(content)
+    }
+}
+
+impl PageSource for Crawler {
+    async fn fetch(&self, url: &Url) -> Result<String, Box<dyn std::error::Error>> {
+        let page = self
+            .pages
+            .lock()
+            .unwrap()
+            .entry(url.clone())
+            .or_default()
+            .clone();
+        let content = page.get_or_try_init(|| self.download(url)).await?;
+
+        Ok(content.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_reserve_slot() {
+        let crawler = Crawler::new(1, Duration::from_millis(100)).unwrap();
+        let a = "https://2022.tgdf.tw/agenda".parse().unwrap();
+        let b = "https://2022.tgdf.tw/speakers".parse().unwrap();
+        let other = "https://example.com/".parse().unwrap();
+
+        let first = crawler.reserve_slot(&a);
+        assert_eq!(crawler.reserve_slot(&b), first + Duration::from_millis(100));
+        assert!(crawler.reserve_slot(&other) < first + Duration::from_millis(100));
+    }
+}
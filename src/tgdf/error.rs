@@ -0,0 +1,125 @@
+use iter_tools::Itertools;
+use reqwest::Url;
+use scraper::ElementRef;
+use std::fmt::Display;
+
+/// What to do when a session fails to be scraped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ErrorPolicy {
+    /// Abort the whole scrape
+    Fail,
+    /// Report the broken session and continue with the rest
+    Skip,
+}
+
+#[derive(Debug)]
+pub enum ScrapeErrorKind {
+    /// The page could not be fetched.
+    Fetch(Box<dyn std::error::Error>),
+    /// No element matches the selector.
+    MissingElement { selector: &'static str },
+    /// The element matched by the selector has no such attribute.
+    MissingAttribute {
+        selector: &'static str,
+        attribute: &'static str,
+    },
+    /// The text of the element matched by the selector is not a time range.
+    InvalidTime {
+        selector: &'static str,
+        text: String,
+    },
+    /// A link on the page can't be resolved to a URL.
+    InvalidLink { href: String },
+}
+
+/// A page of the event site that doesn't look like what the scraper expects.
+#[derive(Debug)]
+pub struct ScrapeError {
+    /// The page being scraped.
+    pub url: String,
+    pub kind: ScrapeErrorKind,
+    /// Abbreviated HTML of the element the selector was applied to.
+    pub context: String,
+}
+
+impl ScrapeError {
+    pub(crate) fn new(url: &Url, element: Option<ElementRef>, kind: ScrapeErrorKind) -> Self {
+        Self {
+            url: url.to_string(),
+            kind,
+            context: element.map(abbreviate).unwrap_or_default(),
+        }
+    }
+
+    pub(crate) fn fetch(url: &Url, err: Box<dyn std::error::Error>) -> Self {
+        Self::new(url, None, ScrapeErrorKind::Fetch(err))
+    }
+}
+
+/// Collapse whitespace and cut the element's HTML short enough for a message.
+fn abbreviate(element: ElementRef) -> String {
+    const MAX_CHARS: usize = 120;
+    let html = element.html().split_whitespace().join(" ");
+    match html.char_indices().nth(MAX_CHARS) {
+        Some((i, _)) => format!("{}...", &html[..i]),
+        None => html,
+    }
+}
+
+impl Display for ScrapeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: ", self.url)?;
+        match &self.kind {
+            ScrapeErrorKind::Fetch(err) => write!(f, "failed to fetch: {}", err)?,
+            ScrapeErrorKind::MissingElement { selector } => {
+                write!(f, "no element matches `{}`", selector)?
+            }
+            ScrapeErrorKind::MissingAttribute {
+                selector,
+                attribute,
+            } => write!(f, "`{}` has no `{}` attribute", selector, attribute)?,
+            ScrapeErrorKind::InvalidTime { selector, text } => {
+                write!(f, "`{}` is not a time range: {:?}", selector, text)?
+            }
+            ScrapeErrorKind::InvalidLink { href } => write!(f, "invalid link {:?}", href)?,
+        }
+        if !self.context.is_empty() {
+            write!(f, " in `{}`", self.context)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for ScrapeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match &self.kind {
+            ScrapeErrorKind::Fetch(err) => Some(err.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use scraper::{Html, Selector};
+
+    #[test]
+    fn test_display() {
+        let page = Html::parse_fragment("<div class=\"session\">\n  <h6>Keynote</h6>\n</div>");
+        let session = page
+            .select(&Selector::parse(".session").unwrap())
+            .next()
+            .unwrap();
+        let err = ScrapeError::new(
+            &"https://2022.tgdf.tw/agenda".parse().unwrap(),
+            Some(session),
+            ScrapeErrorKind::MissingElement { selector: "p" },
+        );
+        assert_eq!(
+            err.to_string(),
+            "https://2022.tgdf.tw/agenda: no element matches `p` in `<div class=\"session\"> <h6>Keynote</h6> </div>`"
+        );
+    }
+}
@@ -0,0 +1,118 @@
+use super::Program;
+use std::{fs, path::Path};
+
+/// Format of an agenda file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Format {
+    Json,
+    Yaml,
+}
+
+impl Format {
+    /// Guess the format from the file extension, JSON unless it's `.yaml` or `.yml`.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("yaml" | "yml") => Self::Yaml,
+            _ => Self::Json,
+        }
+    }
+}
+
+pub fn dump(program: &Program, format: Format) -> Result<String, Box<dyn std::error::Error>> {
+    let content = match format {
+        Format::Json => serde_json::to_string_pretty(program)?,
+        Format::Yaml => serde_yaml::to_string(program)?,
+    };
+
+    Ok(content)
+}
+
+/// Load the agenda exported by [`dump`], possibly edited by hand.
+pub fn load(path: &Path) -> Result<Program, Box<dyn std::error::Error>> {
+    let content = fs::read_to_string(path)?;
+    let mut program: Program = match Format::from_path(path) {
+        Format::Json => serde_json::from_str(&content)?,
+        Format::Yaml => serde_yaml::from_str(&content)?,
+    };
+    // the key is what the sessions refer to, the ID may be left out by hand
+    for (id, speaker) in &mut program.speakers {
+        speaker.id = id.clone();
+    }
+
+    Ok(program)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tgdf::{Agenda, Period, Presenter, Session, SessionKind, Time};
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_round_trip() {
+        let time = (
+            Time {
+                hour: 9,
+                minute: 30,
+            },
+            Time {
+                hour: 10,
+                minute: 0,
+            },
+        );
+        let agendas = vec![Agenda {
+            date: "8/4 (四)".to_string(),
+            periods: vec![Period {
+                time,
+                sessions: vec![Session::test(1, 1, ("9:30", "10:00"), "座談")
+                    .with_kind(SessionKind::Panel)
+                    .with_speakers(&["eve"])],
+            }],
+        }];
+        let mut eve = Presenter::guest("Eve".to_string(), None);
+        eve.id = "eve".to_string();
+        let program = Program {
+            agendas,
+            speakers: BTreeMap::from([(eve.id.clone(), eve)]),
+        };
+
+        for format in [Format::Json, Format::Yaml] {
+            let content = dump(&program, format).unwrap();
+            let program = match format {
+                Format::Json => serde_json::from_str::<Program>(&content).unwrap(),
+                Format::Yaml => serde_yaml::from_str::<Program>(&content).unwrap(),
+            };
+            let session = &program.agendas[0].periods[0].sessions[0];
+            assert_eq!(session.title, "座談");
+            assert_eq!(session.kind, SessionKind::Panel);
+            assert_eq!(session.time, time);
+            assert_eq!(program.presenters(session)[0].name, "Eve");
+        }
+    }
+
+    #[test]
+    fn test_load_speaker_ids() {
+        let path =
+            std::env::temp_dir().join(format!("co-write-agenda-{}.yaml", std::process::id()));
+        fs::write(
+            &path,
+            "agendas: []\n\
+             speakers:\n  \
+               alice: { name: Alice, title: '', link: null, introduction: '' }\n  \
+               bob: { name: Bob, title: '', link: null, introduction: '' }\n",
+        )
+        .unwrap();
+        let program = load(&path).unwrap();
+        assert_eq!(program.speakers["alice"].id, "alice");
+        assert_eq!(program.speakers["bob"].id, "bob");
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_format_from_path() {
+        assert_eq!(Format::from_path(Path::new("agenda.yml")), Format::Yaml);
+        assert_eq!(Format::from_path(Path::new("agenda.json")), Format::Json);
+        assert_eq!(Format::from_path(Path::new("agenda")), Format::Json);
+    }
+}
@@ -0,0 +1,1093 @@
+mod cache;
+mod crawler;
+mod error;
+pub mod file;
+mod report;
+mod source;
+mod speakers;
+
+use crate::markdown;
+use futures::future::{join_all, try_join_all};
+use iter_tools::Itertools;
+use once_cell::sync::Lazy;
+use reqwest::Url;
+use scraper::{ElementRef, Html, Selector};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use speakers::Registry;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::{fmt::Display, str::FromStr};
+
+pub use cache::HttpCache;
+pub use crawler::Crawler;
+pub use error::{ErrorPolicy, ScrapeError, ScrapeErrorKind};
+use report::{DefaultedField, DroppedPeriod};
+pub use report::{ScrapeReport, SessionRef, SkipReason};
+pub use source::{PageSource, Snapshot};
+
+// ref: https://github.com/causal-agent/scraper/issues/53
+macro_rules! selector {
+    ($e: expr) => {{
+        static SELECTOR: Lazy<Selector> = Lazy::new(|| Selector::parse($e).unwrap());
+        &*SELECTOR
+    }};
+}
+
+/// The first element under `$parent` matching `$e`, or a [`ScrapeError`] of `$url`.
+macro_rules! first {
+    ($url: expr, $parent: expr, $e: expr) => {
+        $parent.select(selector!($e)).next().ok_or_else(|| {
+            ScrapeError::new(
+                $url,
+                Some($parent),
+                ScrapeErrorKind::MissingElement { selector: $e },
+            )
+        })
+    };
+}
+
+/// A yearly TGDF event and the site its agenda is scraped from.
+#[derive(Debug, Clone, Serialize)]
+pub struct Event {
+    pub year: u32,
+    #[serde(serialize_with = "serialize_url")]
+    pub base_url: Url,
+}
+
+impl Event {
+    /// The official site of the given year, e.g. `https://2022.tgdf.tw/`.
+    pub fn new(year: u32) -> Self {
+        let base_url = format!("https://{}.tgdf.tw/", year).parse().unwrap();
+        Self { year, base_url }
+    }
+
+    /// Scrape from a mirror or a local copy of the site instead.
+    pub fn with_base_url(year: u32, mut base_url: Url) -> Self {
+        // keep the last path segment when joining relative links
+        if !base_url.path().ends_with('/') {
+            base_url.set_path(&format!("{}/", base_url.path()));
+        }
+        Self { year, base_url }
+    }
+
+    /// Resolve a link found on the site against the base URL.
+    ///
+    /// Site-absolute paths like `/agenda` are resolved under the base URL's
+    /// path so that mirrors hosted in a sub-directory keep working.
+    pub fn url(&self, path: &str) -> Result<Url, Box<dyn std::error::Error>> {
+        Ok(self.base_url.join(path.trim_start_matches('/'))?)
+    }
+}
+
+fn serialize_url<S: Serializer>(url: &Url, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(url.as_str())
+}
+
+#[derive(PartialEq, Eq, Debug, PartialOrd, Ord, Clone, Copy)]
+pub struct Time {
+    pub hour: u8,
+    pub minute: u8,
+}
+
+impl<'de> Deserialize<'de> for Time {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let time = String::deserialize(deserializer)?;
+        time.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+impl Serialize for Time {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl Display for Time {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{:02}", self.hour, self.minute)
+    }
+}
+
+#[derive(Debug)]
+struct TimeParseError {}
+
+impl Display for TimeParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "TimeParseError")
+    }
+}
+
+impl std::error::Error for TimeParseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        None
+    }
+
+    fn cause(&self) -> Option<&dyn std::error::Error> {
+        self.source()
+    }
+}
+
+impl FromStr for Time {
+    type Err = Box<dyn std::error::Error>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let h_and_m = s
+            .split(':')
+            .map(|v| v.parse::<u8>())
+            .collect::<Result<Vec<_>, _>>()?;
+        let (hour, minute) = h_and_m
+            .into_iter()
+            .collect_tuple::<(u8, u8)>()
+            .ok_or(TimeParseError {})?;
+        Ok(Time { hour, minute })
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Presenter {
+    /// Key of the presenter in [`Program::speakers`].
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    pub title: String,
+    /// The speaker page, or the external page of a guest if there is one.
+    pub link: Option<String>,
+    #[serde(default)]
+    pub avatar_link: Option<String>,
+    #[serde(default)]
+    pub company: Option<String>,
+    /// Social and personal websites listed on the speaker page.
+    #[serde(default)]
+    pub links: Vec<PresenterLink>,
+    /// The introduction converted to Markdown.
+    pub introduction: String,
+    /// The introduction as it is on the speaker page.
+    #[serde(default)]
+    pub introduction_html: String,
+}
+
+impl Presenter {
+    /// Scrape a speaker page, `url` should already be resolved by [`Event::url`].
+    pub async fn from_url<S: PageSource>(source: &S, url: Url) -> Result<Self, ScrapeError> {
+        let content = source
+            .fetch(&url)
+            .await
+            .map_err(|err| ScrapeError::fetch(&url, err))?;
+        let page = Html::parse_document(&content);
+        let root = page.root_element();
+
+        let name = first!(&url, root, ".speaker__name")?
+            .text()
+            .collect::<String>();
+        let title = first!(&url, root, ".speaker__title")?
+            .text()
+            .collect::<String>();
+        let introduction_html = first!(&url, root, ".speaker__introduce")?.inner_html();
+        // not every speaker has these, so they are never errors
+        let avatar_link = root
+            .select(selector!(".speaker__avatar img, img.speaker__avatar"))
+            .next()
+            .and_then(|img| img.value().attr("src"))
+            .and_then(|src| url.join(src).ok())
+            .map(String::from);
+        let company = root
+            .select(selector!(".speaker__company"))
+            .next()
+            .map(|e| e.text().collect::<String>().trim().to_string())
+            .filter(|company| !company.is_empty());
+        let links = root
+            .select(selector!(".speaker__links a"))
+            .filter_map(|a| PresenterLink::parse(&url, a))
+            .collect();
+
+        Ok(Self {
+            id: String::new(),
+            name,
+            title,
+            link: Some(url.to_string()),
+            avatar_link,
+            company,
+            links,
+            introduction: markdown::from_html(&introduction_html, Some(&url)),
+            introduction_html,
+        })
+    }
+
+    /// A guest listed by name only, maybe linked to a page outside the event site.
+    pub fn guest(name: String, link: Option<String>) -> Self {
+        Self {
+            id: String::new(),
+            name,
+            title: String::new(),
+            link,
+            avatar_link: None,
+            company: None,
+            links: vec![],
+            introduction: String::new(),
+            introduction_html: String::new(),
+        }
+    }
+}
+
+/// A link on a speaker page, e.g. to the speaker's Twitter or website.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct PresenterLink {
+    /// The link text, or the host name for icon-only links.
+    pub name: String,
+    pub url: String,
+}
+
+impl PresenterLink {
+    /// Links that can't be resolved against the speaker page are dropped.
+    fn parse(page: &Url, a: ElementRef) -> Option<Self> {
+        let url = page.join(a.value().attr("href")?).ok()?;
+        let text = a.text().collect::<String>().trim().to_string();
+        let name = match (text.is_empty(), a.value().attr("title")) {
+            (false, _) => text,
+            (true, Some(title)) => title.to_string(),
+            (true, None) => url.host_str()?.trim_start_matches("www.").to_string(),
+        };
+
+        Some(Self {
+            name,
+            url: url.to_string(),
+        })
+    }
+}
+
+/// What kind of session it is, guessed from its presenters.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionKind {
+    /// Presented by speakers who have speaker pages.
+    Talk,
+    /// Only has guests listed by name, e.g. panels.
+    Panel,
+    /// No presenters but has a session page, e.g. opening and closing.
+    Ceremony,
+    /// Neither presenters nor a session page, e.g. breaks.
+    Break,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Session {
+    pub url: Option<String>,
+    pub kind: SessionKind,
+    pub day: u32,
+    pub track: u32,
+    pub time: (Time, Time),
+    pub title: String,
+    /// IDs of the presenters in [`Program::speakers`].
+    pub speakers: Vec<String>,
+    pub tags: Vec<String>,
+    /// The description converted to Markdown.
+    pub description: String,
+    /// The description as it is on the session page.
+    #[serde(default)]
+    pub description_html: String,
+}
+
+impl Session {
+    /// Identify the session across runs, the session page URL survives
+    /// edits of the title or time slot. Sessions without a page, e.g. breaks
+    /// repeated on the same track, are told apart by their start time.
+    pub fn key(&self) -> String {
+        match &self.url {
+            Some(url) => url.clone(),
+            None => format!(
+                "d{}/t{}/{:02}{:02}/{}",
+                self.day, self.track, self.time.0.hour, self.time.0.minute, self.title
+            ),
+        }
+    }
+
+    /// A short name like `d1-t2-1030` built from the session's slot.
+    pub fn slug(&self) -> String {
+        format!(
+            "d{}-t{}-{:02}{:02}",
+            self.day, self.track, self.time.0.hour, self.time.0.minute
+        )
+    }
+}
+
+/// Sessions built by the tests of every module.
+#[cfg(test)]
+impl Session {
+    /// A talk without a session page, presenters, tags or description.
+    pub fn test(day: u32, track: u32, time: (&str, &str), title: &str) -> Self {
+        Self {
+            url: None,
+            kind: SessionKind::Talk,
+            day,
+            track,
+            time: (time.0.parse().unwrap(), time.1.parse().unwrap()),
+            title: title.to_string(),
+            speakers: vec![],
+            tags: vec![],
+            description: String::new(),
+            description_html: String::new(),
+        }
+    }
+
+    pub fn with_url(mut self, url: &str) -> Self {
+        self.url = Some(url.to_string());
+        self
+    }
+
+    pub fn with_kind(mut self, kind: SessionKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    pub fn with_speakers(mut self, speakers: &[&str]) -> Self {
+        self.speakers = speakers.iter().map(|s| s.to_string()).collect();
+        self
+    }
+
+    pub fn with_tags(mut self, tags: &[&str]) -> Self {
+        self.tags = tags.iter().map(|t| t.to_string()).collect();
+        self
+    }
+
+    pub fn with_description(mut self, description: &str) -> Self {
+        self.description = description.to_string();
+        self
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Period {
+    pub time: (Time, Time),
+    pub sessions: Vec<Session>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Agenda {
+    pub date: String,
+    pub periods: Vec<Period>,
+}
+
+impl Agenda {
+    pub fn sessions(&self) -> Vec<&Session> {
+        self.periods
+            .iter()
+            .flat_map(|p| p.sessions.iter())
+            .collect()
+    }
+}
+
+/// Everything scraped from the event site.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Program {
+    pub agendas: Vec<Agenda>,
+    /// Presenters of every session keyed by [`Presenter::id`].
+    #[serde(default)]
+    pub speakers: BTreeMap<String, Presenter>,
+}
+
+impl Program {
+    pub fn sessions(&self) -> Vec<&Session> {
+        self.agendas.iter().flat_map(|a| a.sessions()).collect()
+    }
+
+    /// The agendas numbered by their day, starting from 1.
+    pub fn days(&self) -> impl Iterator<Item = (u32, &Agenda)> {
+        (1..).zip(&self.agendas)
+    }
+
+    /// Every track that has a session, in order.
+    pub fn tracks(&self) -> BTreeSet<u32> {
+        self.sessions().iter().map(|s| s.track).collect()
+    }
+
+    /// The presenters of a session in the order they are listed.
+    pub fn presenters(&self, session: &Session) -> Vec<&Presenter> {
+        session
+            .speakers
+            .iter()
+            .filter_map(|id| self.speakers.get(id))
+            .collect()
+    }
+}
+
+/// Lowercase the text and join every run of letters and digits with `-`.
+pub fn slugify(text: &str) -> String {
+    let mut slug = String::new();
+    for c in text.chars().flat_map(char::to_lowercase) {
+        if c.is_alphanumeric() {
+            slug.push(c);
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    slug.trim_end_matches('-').to_string()
+}
+
+/// Resolve the `href` of an element matched by `selector` on the page at `url`.
+fn link(
+    event: &Event,
+    url: &Url,
+    element: ElementRef,
+    selector: &'static str,
+) -> Result<Url, ScrapeError> {
+    let href = element.value().attr("href").ok_or_else(|| {
+        ScrapeError::new(
+            url,
+            Some(element),
+            ScrapeErrorKind::MissingAttribute {
+                selector,
+                attribute: "href",
+            },
+        )
+    })?;
+    event.url(href).map_err(|_| {
+        ScrapeError::new(
+            url,
+            Some(element),
+            ScrapeErrorKind::InvalidLink {
+                href: href.to_string(),
+            },
+        )
+    })
+}
+
+/// Parse the time range at the end of a text like `8/4 (四) - 10:00 - 10:40`.
+fn parse_time_range(text: &str) -> Option<(Time, Time)> {
+    let mut parts = text.rsplit(" - ").map(|t| t.trim().parse::<Time>().ok());
+    let to = parts.next()??;
+    let from = parts.next()??;
+    Some((from, to))
+}
+
+/// The time range in the element matched by `selector` under `parent`.
+fn extract_time(
+    url: &Url,
+    parent: ElementRef,
+    selector: &'static str,
+    element: ElementRef,
+) -> Result<(Time, Time), ScrapeError> {
+    let text = element.text().collect::<String>();
+    parse_time_range(&text).ok_or_else(|| {
+        ScrapeError::new(
+            url,
+            Some(parent),
+            ScrapeErrorKind::InvalidTime { selector, text },
+        )
+    })
+}
+
+fn extract_time_from_session_page(url: &Url, page: &Html) -> Result<(Time, Time), ScrapeError> {
+    let root = page.root_element();
+    let time = first!(url, root, ".session__time")?;
+    extract_time(url, root, ".session__time", time)
+}
+
+fn extract_session_description(page: &Html) -> String {
+    page.select(selector!(".session__description"))
+        .next()
+        .map(|e| e.inner_html())
+        .unwrap_or_default()
+}
+
+/// Split a plain-text list of names like `Alice、Bob` into guests.
+fn guests(text: &str) -> impl Iterator<Item = PresenterCard> + '_ {
+    text.split(['、', ',', '，', '/', '&', '＆'])
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(|name| PresenterCard::Guest {
+            name: name.to_string(),
+            link: None,
+        })
+}
+
+/// Someone listed in the presenter block of a session card.
+enum PresenterCard {
+    /// Has a speaker page on the event site.
+    Speaker(Url),
+    /// Listed by name, maybe linked to a page outside the event site.
+    Guest { name: String, link: Option<String> },
+}
+
+impl PresenterCard {
+    /// Collect the presenters in `element` of the agenda page at `url` in order.
+    fn collect(
+        event: &Event,
+        url: &Url,
+        element: ElementRef,
+        presenters: &mut Vec<Self>,
+    ) -> Result<(), ScrapeError> {
+        for child in element.children() {
+            if let Some(text) = child.value().as_text() {
+                presenters.extend(guests(text));
+                continue;
+            }
+            let child = match ElementRef::wrap(child) {
+                Some(child) => child,
+                None => continue,
+            };
+            if child.value().name() != "a" {
+                Self::collect(event, url, child, presenters)?;
+                continue;
+            }
+            let href = link(event, url, child, "p a")?;
+            if href.as_str().starts_with(event.base_url.as_str()) {
+                presenters.push(Self::Speaker(href));
+            } else {
+                presenters.push(Self::Guest {
+                    name: child.text().collect::<String>().trim().to_string(),
+                    link: Some(href.to_string()),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Identifies the presenter across sessions, links with and without a
+    /// trailing `/` are the same page.
+    fn key(&self) -> String {
+        match self {
+            Self::Speaker(url) => url.as_str().trim_end_matches('/').to_string(),
+            Self::Guest {
+                link: Some(link), ..
+            } => link.trim_end_matches('/').to_string(),
+            Self::Guest { name, link: None } => format!("guest:{}", name),
+        }
+    }
+
+    /// What the presenter ID is made from.
+    fn id_hint(&self) -> String {
+        match self {
+            Self::Speaker(url) => url
+                .path_segments()
+                .and_then(|mut segments| segments.rfind(|s| !s.is_empty()))
+                .unwrap_or_default()
+                .to_string(),
+            Self::Guest { name, .. } => name.clone(),
+        }
+    }
+
+    async fn fetch<S: PageSource>(&self, source: &S) -> Result<Presenter, ScrapeError> {
+        match self {
+            Self::Speaker(url) => Presenter::from_url(source, url.clone()).await,
+            Self::Guest { name, link } => Ok(Presenter::guest(name.clone(), link.clone())),
+        }
+    }
+}
+
+/// A session as listed on the agenda page, before its own pages are fetched.
+struct SessionCard {
+    day: u32,
+    track: u32,
+    /// Time of the agenda row, for sessions without a session page.
+    period: (Time, Time),
+    title: String,
+    tags: Vec<String>,
+    url: Option<Url>,
+    kind: SessionKind,
+    /// IDs of the presenters in the [`Registry`].
+    speakers: Vec<String>,
+}
+
+impl SessionCard {
+    /// The link to the session page, which is the first link outside of the
+    /// presenter block.
+    fn session_link<'a>(session: ElementRef<'a>) -> Option<ElementRef<'a>> {
+        let block = session.select(selector!("p")).next();
+        session.select(selector!("a")).find(|a| match block {
+            Some(block) => !a.ancestors().any(|node| node.id() == block.id()),
+            None => true,
+        })
+    }
+
+    /// Parse a `.session` element of the agenda page at `url`, registering
+    /// its presenters in `registry`.
+    ///
+    /// Sessions without speaker pages, e.g. breaks, are not parsed but tell
+    /// why, unless `options.include_speakerless` is set.
+    fn parse(
+        event: &Event,
+        url: &Url,
+        session: ElementRef,
+        (day, track, period): (u32, u32, (Time, Time)),
+        options: &ScrapeOptions,
+        registry: &mut Registry,
+    ) -> Result<Result<Self, SkipReason>, ScrapeError> {
+        let tags = session
+            .select(selector!(".session__badge"))
+            .map(|v| v.text().collect::<String>())
+            .collect::<Vec<_>>();
+        let block = session.select(selector!("p")).next();
+        let mut presenters = vec![];
+        if let Some(block) = block {
+            PresenterCard::collect(event, url, block, &mut presenters)?;
+        }
+        let has_speakers = presenters
+            .iter()
+            .any(|p| matches!(p, PresenterCard::Speaker(_)));
+        if !has_speakers && !options.include_speakerless {
+            return Ok(Err(match block {
+                Some(_) => SkipReason::NoPresenterLinks,
+                None => SkipReason::NoPresenters,
+            }));
+        }
+        let title = first!(url, session, "h6")?.text().collect::<String>();
+        let session_url = match Self::session_link(session) {
+            Some(a) => Some(link(event, url, a, "a")?),
+            None if has_speakers => {
+                return Err(ScrapeError::new(
+                    url,
+                    Some(session),
+                    ScrapeErrorKind::MissingElement { selector: "a" },
+                ))
+            }
+            None => None,
+        };
+        let kind = if has_speakers {
+            SessionKind::Talk
+        } else if !presenters.is_empty() {
+            SessionKind::Panel
+        } else if session_url.is_some() {
+            SessionKind::Ceremony
+        } else {
+            SessionKind::Break
+        };
+
+        Ok(Ok(Self {
+            day,
+            track,
+            period,
+            title,
+            tags,
+            url: session_url,
+            kind,
+            speakers: presenters
+                .into_iter()
+                .map(|p| registry.register(p))
+                .collect(),
+        }))
+    }
+
+    /// Whatever identifies the session card, without failing on broken ones.
+    fn reference(event: &Event, session: ElementRef, day: u32, track: u32) -> SessionRef {
+        SessionRef {
+            day,
+            track,
+            title: session
+                .select(selector!("h6"))
+                .next()
+                .map(|h| h.text().collect()),
+            url: Self::session_link(session)
+                .and_then(|a| a.value().attr("href"))
+                .and_then(|href| event.url(href).ok())
+                .map(String::from),
+        }
+    }
+
+    fn to_reference(&self) -> SessionRef {
+        SessionRef {
+            day: self.day,
+            track: self.track,
+            title: Some(self.title.clone()),
+            url: self.url.as_ref().map(Url::to_string),
+        }
+    }
+
+    async fn fetch<S: PageSource>(
+        self,
+        source: &S,
+        registry: &Registry,
+    ) -> Result<Session, ScrapeError> {
+        let (_, page) = futures::try_join!(
+            try_join_all(self.speakers.iter().map(|id| registry.fetch(id, source))),
+            async {
+                match &self.url {
+                    Some(url) => source
+                        .fetch(url)
+                        .await
+                        .map(Some)
+                        .map_err(|err| ScrapeError::fetch(url, err)),
+                    None => Ok(None),
+                }
+            },
+        )?;
+        let (time, description_html) = match (&self.url, page) {
+            (Some(url), Some(page)) => {
+                let page = Html::parse_document(&page);
+                (
+                    extract_time_from_session_page(url, &page)?,
+                    extract_session_description(&page),
+                )
+            }
+            _ => (self.period, String::new()),
+        };
+
+        let description = markdown::from_html(&description_html, self.url.as_ref());
+        Ok(Session {
+            url: self.url.map(String::from),
+            kind: self.kind,
+            day: self.day,
+            track: self.track,
+            time,
+            title: self.title,
+            speakers: self.speakers,
+            tags: self.tags,
+            description,
+            description_html,
+        })
+    }
+}
+
+/// How to scrape the agenda.
+#[derive(Debug, Clone, Copy)]
+pub struct ScrapeOptions {
+    pub policy: ErrorPolicy,
+    /// Also keep sessions without speaker pages, e.g. panels and breaks.
+    pub include_speakerless: bool,
+}
+
+/// Scrape the agenda of the event.
+///
+/// Speaker and session pages are requested concurrently, how many of them
+/// are in flight is up to the `source`, and every speaker page is fetched
+/// once however many sessions the speaker presents. Sessions that fail to be
+/// scraped are handled according to `options.policy`, while errors on the
+/// layout of the agenda itself always fail. What was parsed or skipped is
+/// recorded in `report`, also when the scrape fails.
+pub async fn fetch<S: PageSource>(
+    event: &Event,
+    source: &S,
+    options: &ScrapeOptions,
+    report: &mut ScrapeReport,
+) -> Result<Program, ScrapeError> {
+    let policy = options.policy;
+    let mut registry = Registry::default();
+    let url = event
+        .url("/agenda")
+        .map_err(|err| ScrapeError::fetch(&event.base_url, err))?;
+    let url = &url;
+    let agendas = source
+        .fetch(url)
+        .await
+        .map_err(|err| ScrapeError::fetch(url, err))?;
+    let agendas = Html::parse_document(&agendas);
+    let mut agenda_storage = vec![];
+    for (day, agenda) in agendas.select(selector!(".agenda")).enumerate() {
+        let day = (day + 1) as u32;
+        let date = first!(url, agenda, ".agenda__label")?.text().join(" ");
+        let mut periods = vec![];
+        for period in agenda.select(selector!(".agenda__row")) {
+            let time = first!(url, period, ".agenda__time")?;
+            let time = extract_time(url, period, ".agenda__time", time)?;
+            let mut cards = vec![];
+            for (i, sessions) in period.select(selector!(".agenda__sessions")).enumerate() {
+                let track = (i + 1) as u32;
+                for session in sessions.select(selector!(".session")) {
+                    let reference = SessionCard::reference(event, session, day, track);
+                    let slot = (day, track, time);
+                    match SessionCard::parse(event, url, session, slot, options, &mut registry) {
+                        Ok(Ok(card)) => cards.push(card),
+                        Ok(Err(reason)) => report.skip(reference, reason),
+                        Err(err) => report.handle(policy, reference, err)?,
+                    }
+                }
+            }
+            if cards.is_empty() {
+                report.dropped_periods.push(DroppedPeriod { day, time });
+                continue;
+            }
+            periods.push((time, cards));
+        }
+        agenda_storage.push((date, periods));
+    }
+
+    let agenda_storage = {
+        // the page fetching futures share the registry
+        let registry = &registry;
+        join_all(
+            agenda_storage
+                .into_iter()
+                .map(|(date, periods)| async move {
+                    let periods = join_all(periods.into_iter().map(|(time, cards)| async move {
+                        let sessions = join_all(cards.into_iter().map(|card| async move {
+                            (card.to_reference(), card.fetch(source, registry).await)
+                        }))
+                        .await;
+                        (time, sessions)
+                    }))
+                    .await;
+                    (date, periods)
+                }),
+        )
+        .await
+    };
+    let mut agendas = vec![];
+    for (day, (date, periods)) in agenda_storage.into_iter().enumerate() {
+        let day = (day + 1) as u32;
+        let mut period_storage = vec![];
+        for (time, sessions) in periods {
+            let mut session_storage = vec![];
+            for (reference, session) in sessions {
+                match session {
+                    Ok(session) => {
+                        if session.description.is_empty() {
+                            report.defaulted.push(DefaultedField {
+                                session: reference.clone(),
+                                field: "description",
+                            });
+                        }
+                        report.parsed.push(reference);
+                        session_storage.push(session);
+                    }
+                    Err(err) => report.handle(policy, reference, err)?,
+                }
+            }
+            if session_storage.is_empty() {
+                report.dropped_periods.push(DroppedPeriod { day, time });
+                continue;
+            }
+            period_storage.push(Period {
+                time,
+                sessions: session_storage,
+            });
+        }
+        agendas.push(Agenda {
+            date,
+            periods: period_storage,
+        });
+    }
+    let program = Program {
+        speakers: BTreeMap::new(),
+        agendas,
+    };
+    let used = program
+        .sessions()
+        .into_iter()
+        .flat_map(|s| s.speakers.iter().map(String::as_str))
+        .collect::<HashSet<_>>();
+    let speakers = registry.into_speakers(&used);
+
+    Ok(Program {
+        speakers,
+        ..program
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_fetch_snapshot() {
+        let event = Event::new(2022);
+        let snapshot = Snapshot::new(concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/snapshot"));
+        let options = ScrapeOptions {
+            policy: ErrorPolicy::Skip,
+            include_speakerless: false,
+        };
+        let mut report = ScrapeReport::default();
+        let program = fetch(&event, &snapshot, &options, &mut report)
+            .await
+            .unwrap();
+
+        let agendas = &program.agendas;
+        assert_eq!(agendas.len(), 1);
+        assert_eq!(agendas[0].date, "8/4 (四)");
+        // the check-in, the panel and the broken session are skipped
+        assert_eq!(agendas[0].periods.len(), 1);
+        // Dave's page is broken, so the only session of Dave is skipped
+        assert_eq!(
+            program.speakers.keys().collect::<Vec<_>>(),
+            vec!["alice", "bob", "carol"]
+        );
+
+        let sessions = program.sessions();
+        assert_eq!(sessions.len(), 2);
+        assert_eq!(sessions[0].title, "即時渲染的過去與未來");
+        assert_eq!(sessions[0].track, 1);
+        assert_eq!(sessions[0].tags, vec!["程式", "技術"]);
+        assert_eq!(sessions[0].speakers, vec!["alice"]);
+        let alice = program.presenters(sessions[0])[0];
+        assert_eq!(alice.id, "alice");
+        assert_eq!(alice.name, "Alice");
+        assert_eq!(sessions[0].kind, SessionKind::Talk);
+        assert_eq!(
+            alice.link.as_deref(),
+            Some("https://2022.tgdf.tw/speakers/alice")
+        );
+        assert_eq!(
+            sessions[0].description,
+            "從光柵化到**路徑追蹤**。\n\n- 延遲渲染\n- [投影片](https://example.com/slides)"
+        );
+        assert!(sessions[0]
+            .description_html
+            .contains("<ul class=\"session__list\">"));
+        assert_eq!(alice.introduction, "專注於即時渲染。");
+        assert_eq!(
+            alice.avatar_link.as_deref(),
+            Some("https://2022.tgdf.tw/images/alice.png")
+        );
+        assert_eq!(alice.company.as_deref(), Some("Example Studio"));
+        assert_eq!(
+            alice.links,
+            vec![
+                PresenterLink {
+                    name: "Twitter".to_string(),
+                    url: "https://twitter.com/alice".to_string(),
+                },
+                PresenterLink {
+                    name: "alice.dev".to_string(),
+                    url: "https://www.alice.dev/".to_string(),
+                },
+            ]
+        );
+        let bob = &program.speakers["bob"];
+        assert_eq!(bob.avatar_link, None);
+        assert!(bob.links.is_empty());
+        assert_eq!(sessions[1].track, 2);
+        assert_eq!(
+            sessions[1].time.1,
+            Time {
+                hour: 10,
+                minute: 30
+            }
+        );
+        assert_eq!(sessions[1].speakers, vec!["bob", "carol"]);
+        assert_eq!(sessions[1].description, "");
+
+        assert_eq!(report.parsed.len(), 2);
+        assert_eq!(report.skipped.len(), 3);
+        assert!(matches!(report.skipped[0].reason, SkipReason::NoPresenters));
+        assert_eq!(report.skipped[0].session.title.as_deref(), Some("報到"));
+        assert!(matches!(
+            report.skipped[1].reason,
+            SkipReason::NoPresenterLinks
+        ));
+        assert!(matches!(report.skipped[2].reason, SkipReason::Error { .. }));
+        assert_eq!(report.dropped_periods.len(), 1);
+        assert_eq!(report.defaulted.len(), 1);
+        assert_eq!(report.defaulted[0].field, "description");
+    }
+
+    #[tokio::test]
+    async fn test_fetch_snapshot_fail_fast() {
+        let event = Event::new(2022);
+        let snapshot = Snapshot::new(concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/snapshot"));
+        let options = ScrapeOptions {
+            policy: ErrorPolicy::Fail,
+            include_speakerless: false,
+        };
+        let err = fetch(&event, &snapshot, &options, &mut ScrapeReport::default())
+            .await
+            .unwrap_err();
+
+        assert_eq!(err.url, "https://2022.tgdf.tw/speakers/dave");
+        assert!(matches!(
+            err.kind,
+            ScrapeErrorKind::MissingElement {
+                selector: ".speaker__title"
+            }
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_snapshot_speakerless() {
+        let event = Event::new(2022);
+        let snapshot = Snapshot::new(concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/snapshot"));
+        let options = ScrapeOptions {
+            policy: ErrorPolicy::Skip,
+            include_speakerless: true,
+        };
+        let mut report = ScrapeReport::default();
+        let program = fetch(&event, &snapshot, &options, &mut report)
+            .await
+            .unwrap();
+
+        let sessions = program.sessions();
+        assert_eq!(sessions.len(), 4);
+        let check_in = sessions[0];
+        assert_eq!(check_in.kind, SessionKind::Break);
+        assert_eq!(check_in.url, None);
+        assert_eq!(
+            check_in.time.0,
+            Time {
+                hour: 9,
+                minute: 30
+            }
+        );
+        assert_eq!(check_in.key(), "d1/t1/0930/報到");
+
+        let panel = sessions[3];
+        assert_eq!(panel.kind, SessionKind::Panel);
+        assert_eq!(panel.title, "製作人座談");
+        let presenters = program.presenters(panel);
+        assert_eq!(presenters.len(), 2);
+        assert_eq!(presenters[0].name, "Eve");
+        assert_eq!(presenters[0].link, None);
+        assert_eq!(presenters[1].name, "Frank");
+        assert_eq!(
+            presenters[1].link.as_deref(),
+            Some("https://example.com/frank")
+        );
+        assert!(panel.description.contains("聊聊遊戲製作。"));
+    }
+
+    #[test]
+    fn test_session_key() {
+        let talk = Session::test(1, 1, ("9:30", "10:10"), "即時渲染")
+            .with_url("https://2022.tgdf.tw/agenda/rendering");
+        assert_eq!(talk.key(), "https://2022.tgdf.tw/agenda/rendering");
+        let morning = Session::test(1, 1, ("10:10", "10:30"), "休息").with_kind(SessionKind::Break);
+        let afternoon =
+            Session::test(1, 1, ("15:00", "15:20"), "休息").with_kind(SessionKind::Break);
+        assert_eq!(morning.key(), "d1/t1/1010/休息");
+        assert_eq!(afternoon.key(), "d1/t1/1500/休息");
+    }
+
+    #[test]
+    fn test_parse_time_range() {
+        let range = (
+            Time { hour: 9, minute: 0 },
+            Time {
+                hour: 9,
+                minute: 40,
+            },
+        );
+        assert_eq!(parse_time_range("9:00 - 9:40"), Some(range));
+        assert_eq!(parse_time_range("8/4 (四) - 9:00 - 9:40 "), Some(range));
+        assert_eq!(parse_time_range("9:00"), None);
+    }
+
+    #[test]
+    fn test_event_url() {
+        let event = Event::new(2023);
+        assert_eq!(
+            event.url("/agenda").unwrap().as_str(),
+            "https://2023.tgdf.tw/agenda"
+        );
+
+        let mirror = Event::with_base_url(2022, "http://localhost:8000/tgdf".parse().unwrap());
+        assert_eq!(
+            mirror.url("/speakers/foo").unwrap().as_str(),
+            "http://localhost:8000/tgdf/speakers/foo"
+        );
+    }
+
+    #[test]
+    fn test_time_order() {
+        assert!(
+            Time {
+                hour: 10,
+                minute: 10
+            } < Time {
+                hour: 11,
+                minute: 0
+            }
+        );
+    }
+}
@@ -0,0 +1,132 @@
+use super::{ErrorPolicy, ScrapeError, Time};
+use serde::Serialize;
+use std::fmt::Display;
+
+/// A session on the agenda page, as much of it as could be parsed.
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionRef {
+    pub day: u32,
+    pub track: u32,
+    pub title: Option<String>,
+    pub url: Option<String>,
+}
+
+impl Display for SessionRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "D{} T{}", self.day, self.track)?;
+        match (&self.title, &self.url) {
+            (Some(title), _) => write!(f, " {}", title),
+            (None, Some(url)) => write!(f, " {}", url),
+            (None, None) => Ok(()),
+        }
+    }
+}
+
+/// Why a session on the agenda page has no note.
+#[derive(Debug, Serialize)]
+#[serde(tag = "reason", rename_all = "snake_case")]
+pub enum SkipReason {
+    /// The session card has no `<p>` block listing its presenters.
+    NoPresenters,
+    /// The presenter block has no links to speaker pages.
+    NoPresenterLinks,
+    /// Scraping failed and [`ErrorPolicy::Skip`] is in effect.
+    Error { message: String },
+}
+
+impl Display for SkipReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NoPresenters => write!(f, "no presenters"),
+            Self::NoPresenterLinks => write!(f, "no links to speaker pages"),
+            Self::Error { message } => write!(f, "{}", message),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct SkippedSession {
+    #[serde(flatten)]
+    pub session: SessionRef,
+    #[serde(flatten)]
+    pub reason: SkipReason,
+}
+
+/// A row of the agenda left without any session.
+#[derive(Debug, Serialize)]
+pub struct DroppedPeriod {
+    pub day: u32,
+    pub time: (Time, Time),
+}
+
+/// A session field missing on the site and left empty.
+#[derive(Debug, Serialize)]
+pub struct DefaultedField {
+    #[serde(flatten)]
+    pub session: SessionRef,
+    pub field: &'static str,
+}
+
+/// What the scraper made of the agenda, to tell whether the notes are complete.
+#[derive(Debug, Default, Serialize)]
+pub struct ScrapeReport {
+    pub parsed: Vec<SessionRef>,
+    pub skipped: Vec<SkippedSession>,
+    pub dropped_periods: Vec<DroppedPeriod>,
+    pub defaulted: Vec<DefaultedField>,
+}
+
+impl ScrapeReport {
+    pub(crate) fn skip(&mut self, session: SessionRef, reason: SkipReason) {
+        self.skipped.push(SkippedSession { session, reason });
+    }
+
+    /// Apply the policy to a session level error: record it and keep going
+    /// when skipping, otherwise fail the scrape.
+    pub(crate) fn handle(
+        &mut self,
+        policy: ErrorPolicy,
+        session: SessionRef,
+        err: ScrapeError,
+    ) -> Result<(), ScrapeError> {
+        match policy {
+            ErrorPolicy::Skip => {
+                let message = err.to_string();
+                self.skip(session, SkipReason::Error { message });
+                Ok(())
+            }
+            ErrorPolicy::Fail => Err(err),
+        }
+    }
+}
+
+impl Display for ScrapeReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "parsed {} sessions, skipped {}, dropped {} empty periods",
+            self.parsed.len(),
+            self.skipped.len(),
+            self.dropped_periods.len()
+        )?;
+        for skipped in &self.skipped {
+            writeln!(f, "  skipped {}: {}", skipped.session, skipped.reason)?;
+        }
+        for period in &self.dropped_periods {
+            writeln!(
+                f,
+                "  dropped D{} {}:{:02} - {}:{:02}",
+                period.day,
+                period.time.0.hour,
+                period.time.0.minute,
+                period.time.1.hour,
+                period.time.1.minute
+            )?;
+        }
+        for defaulted in &self.defaulted {
+            writeln!(f, "  empty {} of {}", defaulted.field, defaulted.session)?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,71 @@
+use reqwest::Url;
+use std::path::PathBuf;
+
+/// Where the pages of an event site are read from.
+pub trait PageSource {
+    async fn fetch(&self, url: &Url) -> Result<String, Box<dyn std::error::Error>>;
+}
+
+/// Read pages from a directory of saved HTML files.
+///
+/// A page is looked up by its URL path, so `/agenda` is read from either
+/// `agenda.html` or `agenda/index.html` under the snapshot directory.
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    root: PathBuf,
+}
+
+impl Snapshot {
+    pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+        Self { root: root.into() }
+    }
+
+    fn candidates(&self, url: &Url) -> Vec<PathBuf> {
+        let path = url.path().trim_matches('/');
+        if path.is_empty() {
+            return vec![self.root.join("index.html")];
+        }
+        vec![
+            self.root.join(path),
+            self.root.join(format!("{}.html", path)),
+            self.root.join(path).join("index.html"),
+        ]
+    }
+}
+
+impl PageSource for Snapshot {
+    async fn fetch(&self, url: &Url) -> Result<String, Box<dyn std::error::Error>> {
+        let candidates = self.candidates(url);
+        let path = candidates
+            .iter()
+            .find(|p| p.is_file())
+            .ok_or_else(|| format!("{} is not found in snapshot {:?}", url, self.root))?;
+
+        Ok(tokio::fs::read_to_string(path).await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_candidates() {
+        let snapshot = Snapshot::new("snap");
+        let url = "https://2022.tgdf.tw/speakers/foo/".parse().unwrap();
+        assert_eq!(
+            snapshot.candidates(&url),
+            vec![
+                PathBuf::from("snap/speakers/foo"),
+                PathBuf::from("snap/speakers/foo.html"),
+                PathBuf::from("snap/speakers/foo/index.html"),
+            ]
+        );
+
+        let root = "https://2022.tgdf.tw/".parse().unwrap();
+        assert_eq!(
+            snapshot.candidates(&root),
+            vec![PathBuf::from("snap/index.html")]
+        );
+    }
+}
@@ -0,0 +1,92 @@
+use super::{slugify, PageSource, Presenter, PresenterCard, ScrapeError};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use tokio::sync::OnceCell;
+
+/// Every presenter of the agenda, fetched once however many sessions they
+/// present.
+#[derive(Default)]
+pub(super) struct Registry {
+    /// IDs keyed by [`PresenterCard::key`].
+    ids: HashMap<String, String>,
+    presenters: BTreeMap<String, (PresenterCard, OnceCell<Presenter>)>,
+}
+
+impl Registry {
+    /// Register a presenter and return its ID, which is derived from the
+    /// speaker page or the name.
+    ///
+    /// Presenters whose pages or names give the same ID are told apart by a
+    /// `-2`, `-3`, ... suffix in the order they are registered, so their IDs
+    /// may change when the agenda does.
+    pub fn register(&mut self, card: PresenterCard) -> String {
+        let key = card.key();
+        if let Some(id) = self.ids.get(&key) {
+            return id.clone();
+        }
+        let base = match slugify(&card.id_hint()) {
+            slug if slug.is_empty() => "speaker".to_string(),
+            slug => slug,
+        };
+        let mut id = base.clone();
+        let mut n = 1;
+        while self.presenters.contains_key(&id) {
+            n += 1;
+            id = format!("{}-{}", base, n);
+        }
+        self.ids.insert(key, id.clone());
+        self.presenters.insert(id.clone(), (card, OnceCell::new()));
+        id
+    }
+
+    /// Fetch a registered presenter, only the first call fetches the page.
+    ///
+    /// A failed fetch is not remembered, so every session of a broken
+    /// speaker page gets its own error.
+    pub async fn fetch<S: PageSource>(
+        &self,
+        id: &str,
+        source: &S,
+    ) -> Result<&Presenter, ScrapeError> {
+        let (card, cell) = &self.presenters[id];
+        cell.get_or_try_init(|| async {
+            let mut presenter = card.fetch(source).await?;
+            presenter.id = id.to_string();
+            Ok(presenter)
+        })
+        .await
+    }
+
+    /// The presenters that are fetched and referenced by `used`.
+    pub fn into_speakers(self, used: &HashSet<&str>) -> BTreeMap<String, Presenter> {
+        self.presenters
+            .into_iter()
+            .filter(|(id, _)| used.contains(id.as_str()))
+            .filter_map(|(id, (_, cell))| Some((id, cell.into_inner()?)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn guest(name: &str) -> PresenterCard {
+        PresenterCard::Guest {
+            name: name.to_string(),
+            link: None,
+        }
+    }
+
+    #[test]
+    fn test_register() {
+        let mut registry = Registry::default();
+        let alice = "https://2022.tgdf.tw/speakers/alice".parse().unwrap();
+        assert_eq!(registry.register(PresenterCard::Speaker(alice)), "alice");
+        let alice = "https://2022.tgdf.tw/speakers/alice/".parse().unwrap();
+        assert_eq!(registry.register(PresenterCard::Speaker(alice)), "alice");
+        assert_eq!(registry.register(guest("Alice")), "alice-2");
+        assert_eq!(registry.register(guest("Alice")), "alice-2");
+        assert_eq!(registry.register(guest("夏娃")), "夏娃");
+        assert_eq!(registry.register(guest("?!")), "speaker");
+    }
+}